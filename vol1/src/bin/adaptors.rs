@@ -0,0 +1,403 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+use std::iter::{Peekable, Repeat, Take};
+
+/// Iterator adaptors that `itertools` 0.7 doesn't have, or has with
+/// different guarantees: `kmerge` leaves the order of equal elements
+/// unspecified and `multi_cartesian_product` needs `n` clones of the
+/// iterator and yields nothing at all for `n == 0`.
+pub trait IteratorExt: Iterator + Sized {
+    /// Windows of `size` consecutive elements, starting every `step`
+    /// elements. A trailing window shorter than `size` is dropped.
+    ///
+    /// Panics if `size` or `step` is zero.
+    fn windows_step(self, size: usize, step: usize) -> WindowsStep<Self>
+    where
+        Self::Item: Clone,
+    {
+        assert!(size > 0 && step > 0, "window size and step must be positive");
+        WindowsStep {
+            iter: self,
+            size: size,
+            step: step,
+            window: VecDeque::with_capacity(size),
+            skip: 0,
+        }
+    }
+
+    /// Groups consecutive elements with the same key, yielding each key
+    /// along with its elements.
+    fn chunk_by_key<K, F>(self, key: F) -> ChunkByKey<Self, F>
+    where
+        K: PartialEq,
+        F: FnMut(&Self::Item) -> K,
+    {
+        ChunkByKey {
+            iter: self.peekable(),
+            key: key,
+        }
+    }
+
+    /// Merges an iterator of sorted iterators into one sorted iterator.
+    /// Unlike `Itertools::kmerge`, equal elements come out in the order of
+    /// the iterators they came from.
+    fn merge_sorted<T>(self) -> MergeSorted<<Self::Item as IntoIterator>::IntoIter>
+    where
+        Self::Item: IntoIterator<Item = T>,
+        T: Ord,
+    {
+        let mut iters: Vec<_> = self.map(IntoIterator::into_iter).collect();
+        let mut heap = BinaryHeap::with_capacity(iters.len());
+        for (index, iter) in iters.iter_mut().enumerate() {
+            if let Some(value) = iter.next() {
+                heap.push(Reverse(Head {
+                    value: value,
+                    index: index,
+                }));
+            }
+        }
+        MergeSorted {
+            iters: iters,
+            heap: heap,
+        }
+    }
+
+    /// All `n`-element sequences of elements of this iterator, in
+    /// lexicographic order: `cartesian_power(2)` is `iproduct!(xs, xs)`
+    /// with vectors instead of tuples. The elements are collected once,
+    /// so the iterator doesn't have to be `Clone`, and `cartesian_power(0)`
+    /// yields the empty sequence.
+    fn cartesian_power(self, n: usize) -> CartesianPower<Self::Item>
+    where
+        Self::Item: Clone,
+    {
+        let pool: Vec<_> = self.collect();
+        let indices = if n > 0 && pool.is_empty() {
+            None
+        } else {
+            Some(vec![0; n])
+        };
+        CartesianPower {
+            pool: pool,
+            indices: indices,
+        }
+    }
+
+    /// Run-length encoding: each run of equal consecutive elements
+    /// becomes an `(element, run length)` pair.
+    fn run_lengths(self) -> RunLengths<Self>
+    where
+        Self::Item: PartialEq,
+    {
+        RunLengths {
+            iter: self.peekable(),
+        }
+    }
+
+    /// The reverse of `run_lengths()`.
+    fn expand_runs<T>(self) -> ExpandRuns<Self, T>
+    where
+        Self: Iterator<Item = (T, usize)>,
+        T: Clone,
+    {
+        ExpandRuns {
+            iter: self,
+            run: None,
+        }
+    }
+}
+
+impl<I: Iterator> IteratorExt for I {}
+
+pub struct WindowsStep<I: Iterator> {
+    iter: I,
+    size: usize,
+    step: usize,
+    window: VecDeque<I::Item>,
+    skip: usize,
+}
+
+impl<I> Iterator for WindowsStep<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Vec<I::Item>> {
+        // With a step larger than the window, some elements belong to
+        // no window at all.
+        while self.skip > 0 {
+            self.iter.next()?;
+            self.skip -= 1;
+        }
+        while self.window.len() < self.size {
+            let item = self.iter.next()?;
+            self.window.push_back(item);
+        }
+        let window = self.window.iter().cloned().collect();
+        if self.step >= self.size {
+            self.window.clear();
+            self.skip = self.step - self.size;
+        } else {
+            self.window.drain(..self.step);
+        }
+        Some(window)
+    }
+}
+
+pub struct ChunkByKey<I: Iterator, F> {
+    iter: Peekable<I>,
+    key: F,
+}
+
+impl<I, K, F> Iterator for ChunkByKey<I, F>
+where
+    I: Iterator,
+    K: PartialEq,
+    F: FnMut(&I::Item) -> K,
+{
+    type Item = (K, Vec<I::Item>);
+
+    fn next(&mut self) -> Option<(K, Vec<I::Item>)> {
+        let first = self.iter.next()?;
+        let key = (self.key)(&first);
+        let mut chunk = vec![first];
+        loop {
+            let same = match self.iter.peek() {
+                Some(item) => (self.key)(item) == key,
+                None => false,
+            };
+            if !same {
+                break;
+            }
+            chunk.extend(self.iter.next());
+        }
+        Some((key, chunk))
+    }
+}
+
+/// The smallest remaining element of one of the merged iterators.
+struct Head<T> {
+    value: T,
+    index: usize,
+}
+
+impl<T: Ord> Ord for Head<T> {
+    fn cmp(&self, other: &Head<T>) -> Ordering {
+        self.value
+            .cmp(&other.value)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl<T: Ord> PartialOrd for Head<T> {
+    fn partial_cmp(&self, other: &Head<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> PartialEq for Head<T> {
+    fn eq(&self, other: &Head<T>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord> Eq for Head<T> {}
+
+pub struct MergeSorted<I: Iterator> {
+    iters: Vec<I>,
+    heap: BinaryHeap<Reverse<Head<I::Item>>>,
+}
+
+impl<I> Iterator for MergeSorted<I>
+where
+    I: Iterator,
+    I::Item: Ord,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let Reverse(head) = self.heap.pop()?;
+        if let Some(value) = self.iters[head.index].next() {
+            self.heap.push(Reverse(Head {
+                value: value,
+                index: head.index,
+            }));
+        }
+        Some(head.value)
+    }
+}
+
+pub struct CartesianPower<T> {
+    pool: Vec<T>,
+    /// Indices into `pool` of the next sequence, `None` when done.
+    indices: Option<Vec<usize>>,
+}
+
+impl<T: Clone> Iterator for CartesianPower<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let sequence = {
+            let indices = self.indices.as_ref()?;
+            indices.iter().map(|&i| self.pool[i].clone()).collect()
+        };
+        // Advance the indices like an odometer.
+        let mut done = true;
+        if let Some(ref mut indices) = self.indices {
+            for i in indices.iter_mut().rev() {
+                *i += 1;
+                if *i < self.pool.len() {
+                    done = false;
+                    break;
+                }
+                *i = 0;
+            }
+        }
+        if done {
+            self.indices = None;
+        }
+        Some(sequence)
+    }
+}
+
+pub struct RunLengths<I: Iterator> {
+    iter: Peekable<I>,
+}
+
+impl<I> Iterator for RunLengths<I>
+where
+    I: Iterator,
+    I::Item: PartialEq,
+{
+    type Item = (I::Item, usize);
+
+    fn next(&mut self) -> Option<(I::Item, usize)> {
+        let item = self.iter.next()?;
+        let mut count = 1;
+        while self.iter.peek() == Some(&item) {
+            self.iter.next();
+            count += 1;
+        }
+        Some((item, count))
+    }
+}
+
+pub struct ExpandRuns<I, T> {
+    iter: I,
+    run: Option<Take<Repeat<T>>>,
+}
+
+impl<I, T> Iterator for ExpandRuns<I, T>
+where
+    I: Iterator<Item = (T, usize)>,
+    T: Clone,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(item) = self.run.as_mut().and_then(|run| run.next()) {
+                return Some(item);
+            }
+            let (item, count) = self.iter.next()?;
+            self.run = Some(::std::iter::repeat(item).take(count));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IteratorExt;
+
+    #[test]
+    fn windows_overlapping() {
+        let windows: Vec<_> = (1..8).windows_step(3, 2).collect();
+        assert_eq!(windows, vec![vec![1, 2, 3], vec![3, 4, 5], vec![5, 6, 7]]);
+    }
+
+    #[test]
+    fn windows_with_gaps() {
+        let windows: Vec<_> = (1..10).windows_step(2, 3).collect();
+        assert_eq!(windows, vec![vec![1, 2], vec![4, 5], vec![7, 8]]);
+    }
+
+    #[test]
+    fn windows_drop_incomplete() {
+        assert_eq!((1..3).windows_step(3, 1).count(), 0);
+        assert_eq!((1..6).windows_step(2, 2).collect::<Vec<_>>(), vec![vec![1, 2], vec![3, 4]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn windows_zero_step() {
+        (1..10).windows_step(2, 0);
+    }
+
+    #[test]
+    fn chunk_words_by_length() {
+        let words = "to be or not to be".split(' ');
+        let chunks: Vec<_> = words.chunk_by_key(|word| word.len()).collect();
+        assert_eq!(
+            chunks,
+            vec![(2, vec!["to", "be", "or"]), (3, vec!["not"]), (2, vec!["to", "be"])]
+        );
+    }
+
+    #[test]
+    fn chunk_empty() {
+        assert_eq!((0..0).chunk_by_key(|x| x % 2).count(), 0);
+    }
+
+    #[test]
+    fn merge_sorted_iterators() {
+        let even = (1..10).map(|x| x * 2);
+        let odd = (1..5).map(|x| x * 2 + 1);
+        let merged: Vec<_> = vec![even.collect::<Vec<_>>(), odd.collect(), vec![], vec![1, 20]]
+            .into_iter()
+            .merge_sorted()
+            .collect();
+        assert_eq!(merged, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 14, 16, 18, 20]);
+    }
+
+    #[test]
+    fn merge_is_stable() {
+        let merged: Vec<_> = vec![vec![(1, 'a'), (2, 'a')], vec![(1, 'b')]]
+            .into_iter()
+            .merge_sorted()
+            .collect();
+        assert_eq!(merged, vec![(1, 'a'), (1, 'b'), (2, 'a')]);
+    }
+
+    #[test]
+    fn cartesian_square() {
+        let pairs: Vec<_> = vec!['a', 'b', 'c'].into_iter().cartesian_power(2).collect();
+        assert_eq!(pairs.len(), 9);
+        assert_eq!(pairs[0], vec!['a', 'a']);
+        assert_eq!(pairs[1], vec!['a', 'b']);
+        assert_eq!(pairs[8], vec!['c', 'c']);
+    }
+
+    #[test]
+    fn cartesian_edge_cases() {
+        assert_eq!((1..4).cartesian_power(0).collect::<Vec<_>>(), vec![vec![]]);
+        assert_eq!((1..1).cartesian_power(2).count(), 0);
+        assert_eq!((1..3).cartesian_power(3).count(), 8);
+    }
+
+    #[test]
+    fn run_length_roundtrip() {
+        let text = "aaabccddddx";
+        let runs: Vec<_> = text.chars().run_lengths().collect();
+        assert_eq!(runs, vec![('a', 3), ('b', 1), ('c', 2), ('d', 4), ('x', 1)]);
+        let decoded: String = runs.into_iter().expand_runs().collect();
+        assert_eq!(decoded, text);
+    }
+
+    #[test]
+    fn expand_zero_length_runs() {
+        let expanded: Vec<_> = vec![(1, 0), (2, 2), (3, 0)].into_iter().expand_runs().collect();
+        assert_eq!(expanded, vec![2, 2]);
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use tau::TAU;
+
+/// An angle, stored in radians.
+///
+/// Arithmetic with `+` and `-` doesn't wrap, so angles can be used to
+/// count full turns as well; the `wrapping_*` methods bring the result
+/// back to a single turn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Angle(f64);
+
+impl Angle {
+    pub fn zero() -> Angle {
+        Angle(0.0)
+    }
+
+    pub fn full_turn() -> Angle {
+        Angle(TAU)
+    }
+
+    pub fn from_radians(radians: f64) -> Angle {
+        Angle(radians)
+    }
+
+    /// One turn is τ radians.
+    pub fn from_turns(turns: f64) -> Angle {
+        Angle(turns * TAU)
+    }
+
+    pub fn from_degrees(degrees: f64) -> Angle {
+        Angle::from_turns(degrees / 360.0)
+    }
+
+    pub fn from_gradians(gradians: f64) -> Angle {
+        Angle::from_turns(gradians / 400.0)
+    }
+
+    pub fn to_radians(self) -> f64 {
+        self.0
+    }
+
+    pub fn to_turns(self) -> f64 {
+        self.0 / TAU
+    }
+
+    pub fn to_degrees(self) -> f64 {
+        self.to_turns() * 360.0
+    }
+
+    pub fn to_gradians(self) -> f64 {
+        self.to_turns() * 400.0
+    }
+
+    /// The same direction, between 0 (inclusive) and τ (exclusive).
+    pub fn normalized(self) -> Angle {
+        // The remainder is exact, but keeps the sign of the angle.
+        let radians = self.0 % TAU;
+        let radians = if radians < 0.0 { radians + TAU } else { radians };
+        // Tiny negative angles can round up to a full turn, and -0.0
+        // should come out as plain zero.
+        if radians >= TAU || radians == 0.0 {
+            Angle(0.0)
+        } else {
+            Angle(radians)
+        }
+    }
+
+    /// The same direction, between -τ/2 (exclusive) and τ/2 (inclusive).
+    pub fn normalized_signed(self) -> Angle {
+        let Angle(radians) = self.normalized();
+        if radians > TAU / 2.0 {
+            Angle(radians - TAU)
+        } else {
+            Angle(radians)
+        }
+    }
+
+    pub fn wrapping_add(self, other: Angle) -> Angle {
+        (self + other).normalized()
+    }
+
+    pub fn wrapping_sub(self, other: Angle) -> Angle {
+        (self - other).normalized()
+    }
+
+    pub fn wrapping_mul(self, factor: f64) -> Angle {
+        (self * factor).normalized()
+    }
+
+    /// The shortest rotation from `self` to `other`, positive when
+    /// counterclockwise.
+    pub fn difference(self, other: Angle) -> Angle {
+        (other - self).normalized_signed()
+    }
+
+    pub fn sin(self) -> f64 {
+        self.0.sin()
+    }
+
+    pub fn cos(self) -> f64 {
+        self.0.cos()
+    }
+
+    pub fn tan(self) -> f64 {
+        self.0.tan()
+    }
+
+    pub fn sin_cos(self) -> (f64, f64) {
+        self.0.sin_cos()
+    }
+
+    pub fn asin(value: f64) -> Angle {
+        Angle(value.asin())
+    }
+
+    pub fn acos(value: f64) -> Angle {
+        Angle(value.acos())
+    }
+
+    pub fn atan(value: f64) -> Angle {
+        Angle(value.atan())
+    }
+
+    /// The direction of the point `(x, y)`, see `f64::atan2()`.
+    pub fn atan2(y: f64, x: f64) -> Angle {
+        Angle(y.atan2(x))
+    }
+}
+
+/// Lets an `Angle` go wherever nalgebra expects radians, e.g.
+/// `Rotation2::new(angle.into())`.
+impl From<Angle> for f64 {
+    fn from(angle: Angle) -> f64 {
+        angle.to_radians()
+    }
+}
+
+/// Formats the angle in degrees, honoring the precision if given.
+impl fmt::Display for Angle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*}°", precision, self.to_degrees()),
+            None => write!(f, "{}°", self.to_degrees()),
+        }
+    }
+}
+
+impl Add for Angle {
+    type Output = Angle;
+
+    fn add(self, other: Angle) -> Angle {
+        Angle(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Angle {
+    fn add_assign(&mut self, other: Angle) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Angle {
+    type Output = Angle;
+
+    fn sub(self, other: Angle) -> Angle {
+        Angle(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Angle {
+    fn sub_assign(&mut self, other: Angle) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Angle {
+    type Output = Angle;
+
+    fn neg(self) -> Angle {
+        Angle(-self.0)
+    }
+}
+
+impl Mul<f64> for Angle {
+    type Output = Angle;
+
+    fn mul(self, factor: f64) -> Angle {
+        Angle(self.0 * factor)
+    }
+}
+
+impl Div<f64> for Angle {
+    type Output = Angle;
+
+    fn div(self, divisor: f64) -> Angle {
+        Angle(self.0 / divisor)
+    }
+}
+
+/// How many times `other` fits in `self`.
+impl Div for Angle {
+    type Output = f64;
+
+    fn div(self, other: Angle) -> f64 {
+        self.0 / other.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Angle::from_degrees(180.0).to_radians(), TAU / 2.0);
+        assert_eq!(Angle::from_turns(0.25).to_degrees(), 90.0);
+        assert_eq!(Angle::from_gradians(100.0).to_turns(), 0.25);
+        assert_eq!(Angle::full_turn().to_gradians(), 400.0);
+    }
+
+    #[test]
+    fn normalized_range() {
+        assert_eq!(Angle::from_turns(1.25).normalized(), Angle::from_turns(0.25));
+        assert_eq!(Angle::from_turns(-0.25).normalized(), Angle::from_turns(0.75));
+        assert_eq!(Angle::full_turn().normalized(), Angle::zero());
+        assert_eq!((-Angle::full_turn()).normalized(), Angle::zero());
+    }
+
+    #[test]
+    fn normalized_negative_zero() {
+        let angle = Angle::from_radians(-0.0).normalized();
+        assert_eq!(angle, Angle::zero());
+        assert!(angle.to_radians().is_sign_positive());
+        assert!(Angle::from_radians(-0.0).normalized_signed().to_radians().is_sign_positive());
+    }
+
+    #[test]
+    fn tiny_negative_angles_wrap_to_zero() {
+        // τ - 1e-20 is just τ in floating point, which is outside the range.
+        for &radians in &[-1e-20, -1e-300, -5e-324] {
+            assert_eq!(Angle::from_radians(radians).normalized(), Angle::zero());
+            assert_eq!(Angle::from_radians(radians).normalized_signed(), Angle::zero());
+        }
+        let angle = Angle::from_radians(-1e-10).normalized();
+        assert!(angle.to_radians() < TAU);
+        assert!(angle.to_radians() > TAU - 2e-10);
+    }
+
+    #[test]
+    fn half_turns() {
+        let half = Angle::from_radians(TAU / 2.0);
+        assert_eq!(half.normalized(), half);
+        assert_eq!(half.normalized_signed(), half);
+        // -τ/2 is the same direction, and the signed range includes only +τ/2.
+        assert_eq!((-half).normalized(), half);
+        assert_eq!((-half).normalized_signed(), half);
+        assert_eq!(Angle::from_turns(1.5).normalized_signed(), half);
+    }
+
+    #[test]
+    fn shortest_difference() {
+        let a = Angle::from_degrees(350.0);
+        let b = Angle::from_degrees(10.0);
+        assert!((a.difference(b).to_degrees() - 20.0).abs() < 1e-12);
+        assert!((b.difference(a).to_degrees() + 20.0).abs() < 1e-12);
+    }
+}
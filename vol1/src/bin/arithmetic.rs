@@ -0,0 +1,145 @@
+use primal::Sieve;
+
+use factorization::{factor_u64, to_u64};
+
+type Factors = Vec<(usize, usize)>;
+
+fn factorize(n: usize, primes: &Sieve) -> Option<Factors> {
+    to_u64(n).and_then(|n| factor_u64(n, primes).ok())
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn lcm(a: usize, b: usize) -> Option<usize> {
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// `base` to the power of `exp`, or `None` on overflow.
+fn checked_power(base: usize, exp: usize) -> Option<usize> {
+    let mut result: usize = 1;
+    for _ in 0..exp {
+        result = result.checked_mul(base)?;
+    }
+    Some(result)
+}
+
+/// Multiplies the results of `f` for every prime power, giving up on
+/// the first overflow.
+fn checked_product<F>(factors: Factors, f: F) -> Option<usize>
+where
+    F: Fn(usize, usize) -> Option<usize>,
+{
+    let mut result: usize = 1;
+    for (p, k) in factors {
+        result = result.checked_mul(f(p, k)?)?;
+    }
+    Some(result)
+}
+
+/// σ(n) - the sum of all divisors of `n`, including 1 and `n` itself,
+/// or `None` if it doesn't fit in a `usize`.
+pub fn sum_of_divisors(n: usize, primes: &Sieve) -> Option<usize> {
+    factorize(n, primes).and_then(|factors| {
+        checked_product(factors, |p, k| {
+            // 1 + p + p^2 + ... + p^k, without computing p^(k + 1)
+            let mut sum: usize = 1;
+            for _ in 0..k {
+                sum = sum.checked_mul(p)?.checked_add(1)?;
+            }
+            Some(sum)
+        })
+    })
+}
+
+/// φ(n) - how many numbers in `1..n + 1` are coprime to `n`.
+pub fn totient(n: usize, primes: &Sieve) -> Option<usize> {
+    factorize(n, primes).and_then(|factors| {
+        checked_product(factors, |p, k| {
+            checked_power(p, k - 1)?.checked_mul(p - 1)
+        })
+    })
+}
+
+/// μ(n) - 0 if `n` has a squared prime factor, otherwise -1 or 1
+/// depending on whether the number of prime factors is odd or even.
+pub fn moebius(n: usize, primes: &Sieve) -> Option<i8> {
+    factorize(n, primes).map(|factors| if factors.iter().any(|&(_, k)| k > 1) {
+        0
+    } else if factors.len() % 2 == 0 {
+        1
+    } else {
+        -1
+    })
+}
+
+/// λ(n) - the smallest `m` such that `a^m ≡ 1 (mod n)` for every `a`
+/// coprime to `n`.
+pub fn carmichael(n: usize, primes: &Sieve) -> Option<usize> {
+    factorize(n, primes).and_then(|factors| {
+        let mut result = 1;
+        for (p, k) in factors {
+            let term = if p == 2 && k > 2 {
+                checked_power(2, k - 2)?
+            } else {
+                checked_power(p, k - 1)?.checked_mul(p - 1)?
+            };
+            result = lcm(result, term)?;
+        }
+        Some(result)
+    })
+}
+
+/// All divisors of `n` in ascending order.
+pub fn divisors(n: usize, primes: &Sieve) -> Option<Vec<usize>> {
+    factorize(n, primes).map(|factors| {
+        let mut result = vec![1];
+        for (p, k) in factors {
+            let mut multiples = Vec::with_capacity(result.len() * k);
+            let mut power = 1;
+            for _ in 0..k {
+                power *= p;
+                multiples.extend(result.iter().map(|d| d * power));
+            }
+            result.extend(multiples);
+        }
+        result.sort();
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use primal::Sieve;
+
+    use super::*;
+
+    #[test]
+    fn small_numbers() {
+        let sieve = Sieve::new(1000);
+        assert_eq!(sum_of_divisors(2610, &sieve), Some(7020));
+        assert_eq!(totient(2610, &sieve), Some(672));
+        assert_eq!(moebius(2610, &sieve), Some(0));
+        assert_eq!(carmichael(2610, &sieve), Some(84));
+        assert_eq!(divisors(12, &sieve), Some(vec![1, 2, 3, 4, 6, 12]));
+        assert_eq!(sum_of_divisors(0, &sieve), None);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn overflow() {
+        let sieve = Sieve::new(1000);
+        // σ(2^63) = 2^64 - 1 just fits, although 2^64 doesn't.
+        assert_eq!(sum_of_divisors(1 << 63, &sieve), Some(usize::max_value()));
+        // σ(3 * 2^62) = 4 * (2^63 - 1)
+        assert_eq!(sum_of_divisors(3 << 62, &sieve), None);
+        // σ(3^40) fits, even though 3^41 doesn't.
+        assert_eq!(
+            sum_of_divisors(12_157_665_459_056_928_801, &sieve),
+            Some(18_236_498_188_585_393_201)
+        );
+        assert_eq!(totient(1 << 63, &sieve), Some(1 << 62));
+        assert_eq!(carmichael(1 << 63, &sieve), Some(1 << 61));
+    }
+}
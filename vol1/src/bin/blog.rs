@@ -0,0 +1,363 @@
+use std::error::Error;
+use std::fmt;
+
+use postgres::{self, Connection};
+use postgres::rows::Row;
+
+/// A single row of the `blog` table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlogPost {
+    pub id: i32,
+    pub title: String,
+    pub body: String,
+}
+
+impl<'a> From<Row<'a>> for BlogPost {
+    fn from(row: Row<'a>) -> BlogPost {
+        BlogPost {
+            id: row.get("id"),
+            title: row.get("title"),
+            body: row.get("body"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BlogError {
+    Database(postgres::Error),
+    NotFound(i32),
+    /// Pages are numbered from 1 and can't be empty. The offset of the
+    /// first post also has to fit in a `bigint`.
+    InvalidPage { page: u32, per_page: u32 },
+}
+
+impl fmt::Display for BlogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BlogError::Database(ref e) => write!(f, "database error: {}", e),
+            BlogError::NotFound(id) => write!(f, "no blog post with ID={}", id),
+            BlogError::InvalidPage { page, per_page } => {
+                write!(f, "invalid page {} with {} posts per page", page, per_page)
+            }
+        }
+    }
+}
+
+impl Error for BlogError {
+    fn description(&self) -> &str {
+        match *self {
+            BlogError::Database(ref e) => e.description(),
+            BlogError::NotFound(_) => "blog post not found",
+            BlogError::InvalidPage { .. } => "invalid page",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            BlogError::Database(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<postgres::Error> for BlogError {
+    fn from(e: postgres::Error) -> BlogError {
+        BlogError::Database(e)
+    }
+}
+
+pub type BlogResult<T> = Result<T, BlogError>;
+
+// Both columns are nullable in the original table definition.
+const COLUMNS: &'static str = "id, coalesce(title, '') as title, coalesce(body, '') as body";
+
+// Must match the index expression, otherwise Postgres won't use the index.
+const BODY_VECTOR: &'static str = "to_tsvector('english', coalesce(body, ''))";
+
+/// All the queries against the `blog` table in one place.
+pub struct BlogRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> BlogRepository<'a> {
+    pub fn new(conn: &'a Connection) -> BlogRepository<'a> {
+        BlogRepository { conn: conn }
+    }
+
+    /// Creates the table and the full-text search index if they don't
+    /// exist yet. `create index if not exists` needs Postgres 9.5, so the
+    /// index is only created when `pg_indexes` doesn't list it.
+    pub fn create_table(&self) -> BlogResult<()> {
+        self.conn.batch_execute(&format!(
+            "create table if not exists blog (
+                id serial primary key,
+                title varchar(255),
+                body text
+            );
+            do $$
+            begin
+                if not exists (
+                    select 1 from pg_indexes
+                    where schemaname = current_schema() and indexname = 'blog_body_search'
+                ) then
+                    create index blog_body_search on blog using gin ({});
+                end if;
+            end
+            $$;",
+            BODY_VECTOR
+        ))?;
+        Ok(())
+    }
+
+    pub fn create(&self, title: &str, body: &str) -> BlogResult<BlogPost> {
+        let query = format!(
+            "insert into blog (title, body) values ($1, $2) returning {}",
+            COLUMNS
+        );
+        let stmt = self.conn.prepare_cached(&query)?;
+        let rows = stmt.query(&[&title, &body])?;
+        // `returning` always gives back the inserted row.
+        Ok(BlogPost::from(rows.get(0)))
+    }
+
+    pub fn get(&self, id: i32) -> BlogResult<BlogPost> {
+        let query = format!("select {} from blog where id = $1", COLUMNS);
+        let stmt = self.conn.prepare_cached(&query)?;
+        let rows = stmt.query(&[&id])?;
+        let post = rows.iter().next().map(BlogPost::from);
+        post.ok_or(BlogError::NotFound(id))
+    }
+
+    /// Returns the `page`-th (counting from 1) batch of `per_page` posts,
+    /// oldest first.
+    pub fn list(&self, page: u32, per_page: u32) -> BlogResult<Vec<BlogPost>> {
+        let limit = per_page as i64;
+        let offset = match (page as i64 - 1).checked_mul(limit) {
+            Some(offset) if page > 0 && per_page > 0 => offset,
+            _ => {
+                return Err(BlogError::InvalidPage {
+                    page: page,
+                    per_page: per_page,
+                })
+            }
+        };
+        let query = format!(
+            "select {} from blog order by id limit $1 offset $2",
+            COLUMNS
+        );
+        let stmt = self.conn.prepare_cached(&query)?;
+        let rows = stmt.query(&[&limit, &offset])?;
+        Ok(rows.iter().map(BlogPost::from).collect())
+    }
+
+    pub fn count(&self) -> BlogResult<i64> {
+        let stmt = self.conn.prepare_cached("select count(*) from blog")?;
+        let rows = stmt.query(&[])?;
+        Ok(rows.get(0).get(0))
+    }
+
+    /// Saves the title and body of an existing post.
+    pub fn update(&self, post: &BlogPost) -> BlogResult<()> {
+        let stmt = self.conn
+            .prepare_cached("update blog set title = $1, body = $2 where id = $3")?;
+        match stmt.execute(&[&post.title, &post.body, &post.id])? {
+            0 => Err(BlogError::NotFound(post.id)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn delete(&self, id: i32) -> BlogResult<()> {
+        let stmt = self.conn.prepare_cached("delete from blog where id = $1")?;
+        match stmt.execute(&[&id])? {
+            0 => Err(BlogError::NotFound(id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Full-text search in post bodies, best matches first. The `terms`
+    /// are plain words, not the `tsquery` syntax, so user input is safe.
+    pub fn search(&self, terms: &str) -> BlogResult<Vec<BlogPost>> {
+        let query = format!(
+            "select {columns} from blog
+            where {vector} @@ plainto_tsquery('english', $1)
+            order by ts_rank({vector}, plainto_tsquery('english', $1)) desc, id",
+            columns = COLUMNS,
+            vector = BODY_VECTOR
+        );
+        let stmt = self.conn.prepare_cached(&query)?;
+        let rows = stmt.query(&[&terms])?;
+        Ok(rows.iter().map(BlogPost::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
+    use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+    use postgres::{Connection, TlsMode};
+    use time;
+
+    use super::*;
+
+    static CLUSTER_COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    /// A Postgres server running from a fresh data directory, stopped
+    /// and wiped when dropped. Needs `initdb` and `pg_ctl` on `PATH`.
+    struct TempPostgres {
+        data_dir: PathBuf,
+        port: u16,
+    }
+
+    fn run(command: &mut Command) {
+        let status = command
+            .stdout(Stdio::null())
+            .status()
+            .expect("Cannot run PostgreSQL binaries, are they on PATH?");
+        assert!(status.success(), "{:?} failed", command);
+    }
+
+    impl TempPostgres {
+        fn start() -> TempPostgres {
+            let data_dir = env::temp_dir().join(format!(
+                "day11-postgres-{}-{}",
+                time::precise_time_ns(),
+                CLUSTER_COUNT.fetch_add(1, Ordering::SeqCst)
+            ));
+            let _ = fs::remove_dir_all(&data_dir);
+            // Let the OS pick a free port.
+            let port = TcpListener::bind("127.0.0.1:0")
+                .and_then(|listener| listener.local_addr())
+                .expect("Cannot find a free port")
+                .port();
+            run(Command::new("initdb")
+                .arg("-D")
+                .arg(&data_dir)
+                .args(&["-U", "rust", "-A", "trust"]));
+            let options = format!(
+                "-p {} -c listen_addresses=127.0.0.1 -k {}",
+                port,
+                data_dir.display()
+            );
+            run(Command::new("pg_ctl")
+                .arg("-D")
+                .arg(&data_dir)
+                .args(&["-o", &options, "-w", "start"]));
+            TempPostgres {
+                data_dir: data_dir,
+                port: port,
+            }
+        }
+
+        fn connect(&self) -> Connection {
+            let dsn = format!("postgresql://rust@127.0.0.1:{}/postgres", self.port);
+            Connection::connect(dsn.as_str(), TlsMode::None).expect("Connection failed")
+        }
+    }
+
+    impl Drop for TempPostgres {
+        fn drop(&mut self) {
+            let _ = Command::new("pg_ctl")
+                .arg("-D")
+                .arg(&self.data_dir)
+                .args(&["-m", "immediate", "-w", "stop"])
+                .stdout(Stdio::null())
+                .status();
+            let _ = fs::remove_dir_all(&self.data_dir);
+        }
+    }
+
+    /// A fresh server with an empty `blog` table. The server has to outlive
+    /// the connection, so keep both around until the end of the test.
+    fn setup() -> (TempPostgres, Connection) {
+        let server = TempPostgres::start();
+        let conn = server.connect();
+        BlogRepository::new(&conn).create_table().unwrap();
+        (server, conn)
+    }
+
+    // These tests start their own PostgreSQL servers, so they're ignored
+    // by default. Run them with `cargo test --bin day11 -- --ignored`.
+
+    #[test]
+    #[ignore]
+    fn create_and_get() {
+        let (_server, conn) = setup();
+        let repo = BlogRepository::new(&conn);
+        let post = repo.create("Hello", "First post").unwrap();
+        assert_eq!(post.title, "Hello");
+        assert_eq!(post.body, "First post");
+        assert_eq!(repo.get(post.id).unwrap(), post);
+        match repo.get(post.id + 1) {
+            Err(BlogError::NotFound(id)) => assert_eq!(id, post.id + 1),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn list_pages() {
+        let (_server, conn) = setup();
+        let repo = BlogRepository::new(&conn);
+        for i in 1..6 {
+            repo.create(&format!("Post {}", i), "").unwrap();
+        }
+        assert_eq!(repo.count().unwrap(), 5);
+        let titles = |page| {
+            repo.list(page, 2)
+                .unwrap()
+                .into_iter()
+                .map(|post| post.title)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(1), vec!["Post 1", "Post 2"]);
+        assert_eq!(titles(3), vec!["Post 5"]);
+        assert!(titles(4).is_empty());
+        assert!(repo.list(0, 2).is_err());
+        assert!(repo.list(1, 0).is_err());
+        // The offset would overflow a bigint.
+        match repo.list(u32::max_value(), u32::max_value()) {
+            Err(BlogError::InvalidPage { .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(repo.list(u32::max_value(), 1).unwrap().is_empty());
+    }
+
+    #[test]
+    #[ignore]
+    fn update_and_delete() {
+        let (_server, conn) = setup();
+        let repo = BlogRepository::new(&conn);
+        let mut post = repo.create("Draft", "TODO").unwrap();
+        post.title = "Final".to_string();
+        post.body = "Done".to_string();
+        repo.update(&post).unwrap();
+        assert_eq!(repo.get(post.id).unwrap(), post);
+        repo.delete(post.id).unwrap();
+        assert!(repo.get(post.id).is_err());
+        assert!(repo.update(&post).is_err());
+        assert!(repo.delete(post.id).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn full_text_search() {
+        let (_server, conn) = setup();
+        let repo = BlogRepository::new(&conn);
+        let rust = repo.create("Rust", "Rust is a systems programming language").unwrap();
+        let cats = repo.create("Cats", "Cats are better than programmers").unwrap();
+        repo.create("Empty", "").unwrap();
+        let found = repo.search("programming").unwrap();
+        assert_eq!(found, vec![rust.clone()]);
+        // Stemming matches "programmers" too.
+        let found = repo.search("programmer").unwrap();
+        assert_eq!(found, vec![cats]);
+        assert!(repo.search("python").unwrap().is_empty());
+        // tsquery operators are just punctuation here.
+        assert_eq!(repo.search("rust & !").unwrap(), vec![rust]);
+    }
+}
@@ -0,0 +1,411 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use hyper::{self, Client};
+use hyper::client::pool::{Config, Pool};
+use hyper::client::{Body, Response};
+use hyper::header::{Accept, ContentType, Headers};
+use hyper::mime::{Mime, SubLevel, TopLevel};
+use hyper::net::{HttpStream, NetworkConnector};
+use hyper::status::StatusCode;
+use rustc_serialize::{json, Decodable, Encodable};
+use rustc_serialize::json::Json;
+use url::form_urlencoded;
+
+use multipart::Multipart;
+
+#[derive(Debug)]
+pub enum HttpError {
+    Http(hyper::Error),
+    Status { status: StatusCode, body: String },
+    Encode(json::EncoderError),
+    /// The response was expected to be JSON, but said it was something else.
+    ContentType { found: Option<Mime>, body: String },
+    InvalidJson(json::ParserError),
+    Decode(json::DecoderError),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HttpError::Http(ref e) => write!(f, "HTTP error: {}", e),
+            HttpError::Status { status, ref body } => write!(f, "{}: {}", status, body),
+            HttpError::Encode(ref e) => write!(f, "cannot encode request: {}", e),
+            HttpError::ContentType { found: Some(ref mime), .. } => {
+                write!(f, "expected a JSON response, got {}", mime)
+            }
+            HttpError::ContentType { found: None, .. } => {
+                write!(f, "expected a JSON response, got no Content-Type")
+            }
+            HttpError::InvalidJson(ref e) => write!(f, "invalid JSON in response: {}", e),
+            HttpError::Decode(ref e) => write!(f, "unexpected JSON in response: {}", e),
+        }
+    }
+}
+
+impl Error for HttpError {
+    fn description(&self) -> &str {
+        match *self {
+            HttpError::Http(ref e) => e.description(),
+            HttpError::Status { .. } => "unsuccessful status code",
+            HttpError::Encode(ref e) => e.description(),
+            HttpError::ContentType { .. } => "response is not JSON",
+            HttpError::InvalidJson(ref e) => e.description(),
+            HttpError::Decode(ref e) => e.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            HttpError::Http(ref e) => Some(e),
+            HttpError::Encode(ref e) => Some(e),
+            HttpError::InvalidJson(ref e) => Some(e),
+            HttpError::Decode(ref e) => Some(e),
+            HttpError::Status { .. } | HttpError::ContentType { .. } => None,
+        }
+    }
+}
+
+impl From<hyper::Error> for HttpError {
+    fn from(e: hyper::Error) -> HttpError {
+        HttpError::Http(e)
+    }
+}
+
+impl From<io::Error> for HttpError {
+    fn from(e: io::Error) -> HttpError {
+        HttpError::Http(hyper::Error::Io(e))
+    }
+}
+
+impl HttpError {
+    /// Server errors and dropped connections are worth another try,
+    /// anything else would most likely fail the same way again.
+    fn is_transient(&self) -> bool {
+        match *self {
+            HttpError::Status { status, .. } => status.is_server_error(),
+            HttpError::Http(hyper::Error::Io(ref e)) => match e.kind() {
+                io::ErrorKind::ConnectionRefused |
+                io::ErrorKind::ConnectionReset |
+                io::ErrorKind::ConnectionAborted |
+                io::ErrorKind::NotConnected |
+                io::ErrorKind::BrokenPipe |
+                io::ErrorKind::UnexpectedEof => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Plain HTTP connector that gives up on unreachable hosts after `timeout`.
+struct TimeoutConnector {
+    timeout: Option<Duration>,
+}
+
+impl NetworkConnector for TimeoutConnector {
+    type Stream = HttpStream;
+
+    fn connect(&self, host: &str, port: u16, scheme: &str) -> hyper::Result<HttpStream> {
+        if scheme != "http" {
+            return Err(hyper::Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid scheme for Http",
+            )));
+        }
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return Ok(HttpStream(TcpStream::connect((host, port))?)),
+        };
+        let mut last_error = None;
+        for addr in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Ok(HttpStream(stream)),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(hyper::Error::Io(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, "host has no addresses")
+        })))
+    }
+}
+
+pub struct HttpClientBuilder {
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl HttpClientBuilder {
+    pub fn connect_timeout(mut self, timeout: Duration) -> HttpClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Duration) -> HttpClientBuilder {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry failed requests up to `retries` times, waiting `backoff` before
+    /// the first retry and twice as long before each next one.
+    pub fn retries(mut self, retries: u32, backoff: Duration) -> HttpClientBuilder {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn build(self) -> HttpClient {
+        let connector = TimeoutConnector {
+            timeout: self.connect_timeout,
+        };
+        let mut client = Client::with_connector(Pool::with_connector(Config::default(), connector));
+        client.set_read_timeout(self.read_timeout);
+        HttpClient {
+            client: client,
+            retries: self.retries,
+            backoff: self.backoff,
+        }
+    }
+}
+
+fn is_json(mime: &Mime) -> bool {
+    match *mime {
+        Mime(TopLevel::Application, SubLevel::Json, _) => true,
+        Mime(TopLevel::Application, SubLevel::Ext(ref ext), _) => ext.ends_with("+json"),
+        _ => false,
+    }
+}
+
+/// What's left of a successful response once its body has been read.
+struct Reply {
+    headers: Headers,
+    body: String,
+}
+
+impl Reply {
+    fn decode<T: Decodable>(self) -> Result<T, HttpError> {
+        let mime = self.headers.get::<ContentType>().map(|content_type| content_type.0.clone());
+        match mime {
+            Some(ref mime) if is_json(mime) => {}
+            found => {
+                return Err(HttpError::ContentType {
+                    found: found,
+                    body: self.body,
+                })
+            }
+        }
+        let json = Json::from_str(&self.body).map_err(HttpError::InvalidJson)?;
+        T::decode(&mut json::Decoder::new(json)).map_err(HttpError::Decode)
+    }
+}
+
+/// A `hyper::Client` shared by all requests, so that keep-alive connections
+/// get reused. Responses with a status other than 2xx become errors.
+pub struct HttpClient {
+    client: Client,
+    retries: u32,
+    backoff: Duration,
+}
+
+pub type Query<'a> = Vec<(&'a str, &'a str)>;
+
+impl HttpClient {
+    pub fn builder() -> HttpClientBuilder {
+        HttpClientBuilder {
+            connect_timeout: Some(Duration::from_secs(10)),
+            read_timeout: Some(Duration::from_secs(30)),
+            retries: 2,
+            backoff: Duration::from_millis(200),
+        }
+    }
+
+    fn read_response(mut response: Response) -> Result<Reply, HttpError> {
+        let mut body = String::new();
+        response.read_to_string(&mut body)?;
+        if response.status.is_success() {
+            Ok(Reply {
+                headers: response.headers.clone(),
+                body: body,
+            })
+        } else {
+            Err(HttpError::Status {
+                status: response.status,
+                body: body,
+            })
+        }
+    }
+
+    /// Runs `send` until it succeeds, fails for good or runs out of retries.
+    fn execute<F>(&self, send: F) -> Result<Reply, HttpError>
+    where
+        F: Fn(&Client) -> hyper::Result<Response>,
+    {
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+        loop {
+            let result = send(&self.client)
+                .map_err(HttpError::from)
+                .and_then(HttpClient::read_response);
+            match result {
+                Err(ref e) if e.is_transient() && attempt < self.retries => {}
+                result => return result,
+            }
+            thread::sleep(delay);
+            delay = delay * 2;
+            attempt += 1;
+        }
+    }
+
+    pub fn get_content(&self, url: &str) -> Result<String, HttpError> {
+        self.execute(|client| client.get(url).send())
+            .map(|reply| reply.body)
+    }
+
+    pub fn get_json<Resp>(&self, url: &str) -> Result<Resp, HttpError>
+    where
+        Resp: Decodable,
+    {
+        self.execute(|client| client.get(url).header(Accept::json()).send())?
+            .decode()
+    }
+
+    pub fn post_query(&self, url: &str, query: Query) -> Result<String, HttpError> {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(query.iter())
+            .finish();
+        self.execute(|client| {
+            client
+                .post(url)
+                .header(ContentType::form_url_encoded())
+                .body(&body[..])
+                .send()
+        }).map(|reply| reply.body)
+    }
+
+    /// Uploads `form`. The file parts are streamed from disk, so unlike the
+    /// other requests a failed upload is not retried.
+    pub fn post_multipart(&self, url: &str, form: Multipart) -> Result<String, HttpError> {
+        let content_type = form.content_type();
+        let (mut body, len) = form.into_body();
+        let body = match len {
+            Some(len) => Body::SizedBody(&mut body, len),
+            None => Body::ChunkedBody(&mut body),
+        };
+        self.client
+            .post(url)
+            .header(content_type)
+            .body(body)
+            .send()
+            .map_err(HttpError::from)
+            .and_then(HttpClient::read_response)
+            .map(|reply| reply.body)
+    }
+
+    /// Sends `payload` as JSON and decodes the JSON response into `Resp`.
+    pub fn post_json<Req, Resp>(&self, url: &str, payload: &Req) -> Result<Resp, HttpError>
+    where
+        Req: Encodable,
+        Resp: Decodable,
+    {
+        let body = json::encode(payload).map_err(HttpError::Encode)?;
+        self.execute(|client| {
+            client
+                .post(url)
+                .header(ContentType::json())
+                .header(Accept::json())
+                .body(&body[..])
+                .send()
+        })?
+            .decode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(content_type: Option<&str>, body: &str) -> Reply {
+        let mut headers = Headers::new();
+        if let Some(content_type) = content_type {
+            headers.set(ContentType(content_type.parse().unwrap()));
+        }
+        Reply {
+            headers: headers,
+            body: body.to_string(),
+        }
+    }
+
+    fn io_error(kind: io::ErrorKind) -> HttpError {
+        HttpError::from(io::Error::new(kind, "test"))
+    }
+
+    #[test]
+    fn server_errors_are_transient() {
+        let status = |status| HttpError::Status {
+            status: status,
+            body: String::new(),
+        };
+        assert!(status(StatusCode::InternalServerError).is_transient());
+        assert!(status(StatusCode::ServiceUnavailable).is_transient());
+        assert!(!status(StatusCode::BadRequest).is_transient());
+        assert!(!status(StatusCode::NotFound).is_transient());
+        assert!(!status(StatusCode::TooManyRequests).is_transient());
+    }
+
+    #[test]
+    fn dropped_connections_are_transient() {
+        let transient = [
+            io::ErrorKind::ConnectionRefused,
+            io::ErrorKind::ConnectionReset,
+            io::ErrorKind::ConnectionAborted,
+            io::ErrorKind::NotConnected,
+            io::ErrorKind::BrokenPipe,
+            io::ErrorKind::UnexpectedEof,
+        ];
+        for &kind in &transient {
+            assert!(io_error(kind).is_transient(), "{:?}", kind);
+        }
+        for &kind in &[io::ErrorKind::TimedOut, io::ErrorKind::InvalidInput] {
+            assert!(!io_error(kind).is_transient(), "{:?}", kind);
+        }
+        assert!(!HttpError::Http(hyper::Error::TooLarge).is_transient());
+    }
+
+    #[test]
+    fn json_replies() {
+        let numbers: Vec<u32> = reply(Some("application/json"), "[1, 2]").decode().unwrap();
+        assert_eq!(numbers, vec![1, 2]);
+        let problem = reply(Some("application/problem+json; charset=utf-8"), "[]");
+        assert_eq!(problem.decode::<Vec<u32>>().unwrap(), vec![]);
+        match reply(Some("application/json"), "[1, 2").decode::<Vec<u32>>() {
+            Err(HttpError::InvalidJson(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match reply(Some("application/json"), "[1, 2]").decode::<String>() {
+            Err(HttpError::Decode(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn other_content_types() {
+        match reply(Some("text/html"), "<h1>502</h1>").decode::<Vec<u32>>() {
+            Err(HttpError::ContentType { found, body }) => {
+                assert_eq!(found, Some(Mime(TopLevel::Text, SubLevel::Html, vec![])));
+                assert_eq!(body, "<h1>502</h1>");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let error = reply(None, "[]").decode::<Vec<u32>>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected a JSON response, got no Content-Type"
+        );
+    }
+}
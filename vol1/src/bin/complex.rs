@@ -0,0 +1,347 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use image::{ImageBuffer, Rgb};
+use num::complex::{Complex, Complex64};
+use num::{pow, Zero};
+
+use angle::Angle;
+
+/// The `n` complex numbers whose `n`-th power is 1, evenly spaced on the
+/// unit circle starting from 1.
+pub fn roots_of_unity(n: usize) -> Vec<Complex64> {
+    (0..n)
+        .map(|k| {
+            let angle = Angle::from_turns(k as f64 / n as f64);
+            Complex::from_polar(&1.0, &angle.to_radians())
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RootsError {
+    /// A constant polynomial has either no roots or infinitely many.
+    Constant,
+    NoConvergence { iterations: usize },
+}
+
+impl fmt::Display for RootsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RootsError::Constant => write!(f, "a constant polynomial has no isolated roots"),
+            RootsError::NoConvergence { iterations } => {
+                write!(f, "no convergence after {} iterations", iterations)
+            }
+        }
+    }
+}
+
+impl Error for RootsError {
+    fn description(&self) -> &str {
+        match *self {
+            RootsError::Constant => "constant polynomial",
+            RootsError::NoConvergence { .. } => "root finding did not converge",
+        }
+    }
+}
+
+/// A polynomial with complex coefficients, stored from the highest power
+/// down to the constant term, so `[1, 0, -1]` is `z² - 1`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polynomial {
+    coefficients: Vec<Complex64>,
+}
+
+impl Polynomial {
+    pub fn new(coefficients: Vec<Complex64>) -> Polynomial {
+        let leading_zeros = coefficients.iter().take_while(|c| c.is_zero()).count();
+        Polynomial {
+            coefficients: coefficients[leading_zeros..].to_vec(),
+        }
+    }
+
+    /// A polynomial with real coefficients.
+    pub fn real(coefficients: &[f64]) -> Polynomial {
+        Polynomial::new(coefficients.iter().map(|&c| Complex::new(c, 0.0)).collect())
+    }
+
+    /// The degree, with the zero polynomial having degree 0 as well.
+    pub fn degree(&self) -> usize {
+        self.coefficients.len().saturating_sub(1)
+    }
+
+    /// Evaluates the polynomial at `z` using Horner's method.
+    pub fn eval(&self, z: Complex64) -> Complex64 {
+        self.coefficients
+            .iter()
+            .fold(Complex::zero(), |acc, &c| acc * z + c)
+    }
+
+    /// Finds all roots at once with the Durand–Kerner method, repeated
+    /// roots included.
+    pub fn roots(&self) -> Result<Vec<Complex64>, RootsError> {
+        self.durand_kerner(1e-12, 1000)
+    }
+
+    /// Each approximation is repeatedly corrected by `p(z) / Π(z - w)`,
+    /// where `w` are the other approximations, until no correction is
+    /// larger than `tolerance`.
+    pub fn durand_kerner(
+        &self,
+        tolerance: f64,
+        max_iterations: usize,
+    ) -> Result<Vec<Complex64>, RootsError> {
+        let degree = self.degree();
+        if degree == 0 {
+            return Err(RootsError::Constant);
+        }
+        let leading = self.coefficients[0];
+        let monic = Polynomial {
+            coefficients: self.coefficients.iter().map(|&c| c / leading).collect(),
+        };
+        // The usual starting points: powers of a number which is neither
+        // real nor a root of unity.
+        let seed = Complex::new(0.4, 0.9);
+        let mut roots: Vec<Complex64> = (0..degree).map(|k| pow(seed, k)).collect();
+        for _ in 0..max_iterations {
+            let mut largest_step: f64 = 0.0;
+            for i in 0..degree {
+                let z = roots[i];
+                let denominator = roots
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .fold(Complex::new(1.0, 0.0), |acc, (_, &w)| acc * (z - w));
+                let step = monic.eval(z) / denominator;
+                if step.is_finite() {
+                    roots[i] = z - step;
+                    largest_step = largest_step.max(step.norm() / z.norm().max(1.0));
+                }
+            }
+            if largest_step < tolerance {
+                return Ok(roots);
+            }
+        }
+        Err(RootsError::NoConvergence {
+            iterations: max_iterations,
+        })
+    }
+}
+
+/// Converts HSV (all components between 0 and 1) to RGB.
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Rgb<u8> {
+    let sector = (hue * 6.0).floor();
+    let fraction = hue * 6.0 - sector;
+    let p = value * (1.0 - saturation);
+    let q = value * (1.0 - saturation * fraction);
+    let t = value * (1.0 - saturation * (1.0 - fraction));
+    let (r, g, b) = match sector as i32 % 6 {
+        0 => (value, t, p),
+        1 => (q, value, p),
+        2 => (p, value, t),
+        3 => (p, q, value),
+        4 => (t, p, value),
+        _ => (value, p, q),
+    };
+    let channel = |c: f64| (c * 255.0).round() as u8;
+    Rgb([channel(r), channel(g), channel(b)])
+}
+
+/// The color of a single value: the hue shows the argument (red for
+/// positive reals, cyan for negative ones), the brightness cycles
+/// every time the modulus doubles, so zeros and poles stand out as
+/// points where all colors meet and contour lines bunch up.
+fn color(w: Complex64) -> Rgb<u8> {
+    if !w.is_finite() {
+        return Rgb([255, 255, 255]);
+    }
+    let modulus = w.norm();
+    if modulus == 0.0 {
+        return Rgb([0, 0, 0]);
+    }
+    let hue = Angle::from_radians(w.arg()).normalized().to_turns();
+    let contour = modulus.log2() - modulus.log2().floor();
+    hsv_to_rgb(hue, 0.9, 0.6 + 0.4 * contour)
+}
+
+/// A view of the complex plane: the rectangle between `min` and `max`
+/// (lower left and upper right corner).
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+    pub min: Complex64,
+    pub max: Complex64,
+}
+
+impl Viewport {
+    /// A square centered at the origin.
+    pub fn square(half_width: f64) -> Viewport {
+        Viewport {
+            min: Complex::new(-half_width, -half_width),
+            max: Complex::new(half_width, half_width),
+        }
+    }
+
+    /// The point at the center of pixel `(x, y)`, with the imaginary
+    /// axis pointing up.
+    fn point(&self, x: u32, y: u32, width: u32, height: u32) -> Complex64 {
+        let re = self.min.re + (self.max.re - self.min.re) * (x as f64 + 0.5) / width as f64;
+        let im = self.max.im - (self.max.im - self.min.im) * (y as f64 + 0.5) / height as f64;
+        Complex::new(re, im)
+    }
+}
+
+/// Renders `f` over `viewport` using domain coloring.
+pub fn domain_coloring<F>(
+    f: F,
+    viewport: Viewport,
+    width: u32,
+    height: u32,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>>
+where
+    F: Fn(Complex64) -> Complex64,
+{
+    ImageBuffer::from_fn(width, height, |x, y| {
+        color(f(viewport.point(x, y, width, height)))
+    })
+}
+
+/// Like `domain_coloring()`, but saves the image straight to a PNG file.
+pub fn save_domain_coloring<F, P>(
+    f: F,
+    viewport: Viewport,
+    width: u32,
+    height: u32,
+    path: P,
+) -> ::std::io::Result<()>
+where
+    F: Fn(Complex64) -> Complex64,
+    P: AsRef<Path>,
+{
+    domain_coloring(f, viewport, width, height).save(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Complex64, expected: Complex64, tolerance: f64) {
+        assert!(
+            (actual - expected).norm() < tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    /// Roots in a predictable order, for comparing against expected ones.
+    fn sorted(mut roots: Vec<Complex64>) -> Vec<Complex64> {
+        roots.sort_by(|a, b| {
+            (a.re, a.im)
+                .partial_cmp(&(b.re, b.im))
+                .expect("roots should not be NaN")
+        });
+        roots
+    }
+
+    #[test]
+    fn roots_of_unity_are_roots() {
+        assert!(roots_of_unity(0).is_empty());
+        assert_eq!(roots_of_unity(1), vec![Complex::new(1.0, 0.0)]);
+        let roots = roots_of_unity(4);
+        let expected = [
+            Complex::new(1.0, 0.0),
+            Complex::new(0.0, 1.0),
+            Complex::new(-1.0, 0.0),
+            Complex::new(0.0, -1.0),
+        ];
+        for (&root, &expected) in roots.iter().zip(expected.iter()) {
+            assert_close(root, expected, 1e-15);
+        }
+        for root in roots_of_unity(7) {
+            assert_close(pow(root, 7), Complex::new(1.0, 0.0), 1e-14);
+        }
+    }
+
+    #[test]
+    fn leading_zeros_are_dropped() {
+        let p = Polynomial::real(&[0.0, 0.0, 2.0, 1.0]);
+        assert_eq!(p.degree(), 1);
+        assert_eq!(p, Polynomial::real(&[2.0, 1.0]));
+        assert_eq!(
+            Polynomial::real(&[0.0, 3.0]).roots(),
+            Err(RootsError::Constant)
+        );
+        assert_eq!(Polynomial::real(&[]).roots(), Err(RootsError::Constant));
+    }
+
+    #[test]
+    fn simple_roots() {
+        // (z - 1)(z - 2)(z - 3)
+        let roots = sorted(Polynomial::real(&[1.0, -6.0, 11.0, -6.0]).roots().unwrap());
+        for (&root, &expected) in roots.iter().zip([1.0, 2.0, 3.0].iter()) {
+            assert_close(root, Complex::new(expected, 0.0), 1e-12);
+        }
+        // z² + 1, with a leading coefficient that isn't 1
+        let roots = sorted(Polynomial::real(&[2.0, 0.0, 2.0]).roots().unwrap());
+        assert_close(roots[0], Complex::new(0.0, -1.0), 1e-12);
+        assert_close(roots[1], Complex::new(0.0, 1.0), 1e-12);
+    }
+
+    #[test]
+    fn complex_coefficients() {
+        // (z - i)(z - 2 - i) = z² - (2 + 2i)z + (-1 + 2i)
+        let p = Polynomial::new(vec![
+            Complex::new(1.0, 0.0),
+            Complex::new(-2.0, -2.0),
+            Complex::new(-1.0, 2.0),
+        ]);
+        let roots = sorted(p.roots().unwrap());
+        assert_close(roots[0], Complex::new(0.0, 1.0), 1e-12);
+        assert_close(roots[1], Complex::new(2.0, 1.0), 1e-12);
+    }
+
+    #[test]
+    fn repeated_roots() {
+        // Rounding errors keep the approximations of a multiple root apart,
+        // so they only get within about 1e-8 of it.
+        // (z - 1)³
+        for root in Polynomial::real(&[1.0, -3.0, 3.0, -1.0]).roots().unwrap() {
+            assert_close(root, Complex::new(1.0, 0.0), 1e-7);
+        }
+        // (z + 1)²(z - 2)
+        let roots = sorted(Polynomial::real(&[1.0, 0.0, -3.0, -2.0]).roots().unwrap());
+        assert_close(roots[0], Complex::new(-1.0, 0.0), 1e-8);
+        assert_close(roots[1], Complex::new(-1.0, 0.0), 1e-8);
+        assert_close(roots[2], Complex::new(2.0, 0.0), 1e-12);
+    }
+
+    #[test]
+    fn iteration_limit() {
+        let p = Polynomial::real(&[1.0, -6.0, 11.0, -6.0]);
+        assert_eq!(
+            p.durand_kerner(1e-12, 2),
+            Err(RootsError::NoConvergence { iterations: 2 })
+        );
+    }
+
+    #[test]
+    fn primary_and_secondary_colors() {
+        assert_eq!(hsv_to_rgb(0.0, 1.0, 1.0), Rgb([255, 0, 0]));
+        assert_eq!(hsv_to_rgb(1.0 / 6.0, 1.0, 1.0), Rgb([255, 255, 0]));
+        assert_eq!(hsv_to_rgb(1.0 / 3.0, 1.0, 1.0), Rgb([0, 255, 0]));
+        assert_eq!(hsv_to_rgb(0.5, 1.0, 1.0), Rgb([0, 255, 255]));
+        assert_eq!(hsv_to_rgb(2.0 / 3.0, 1.0, 1.0), Rgb([0, 0, 255]));
+        assert_eq!(hsv_to_rgb(5.0 / 6.0, 1.0, 1.0), Rgb([255, 0, 255]));
+        // A full turn of hue is red again.
+        assert_eq!(hsv_to_rgb(1.0, 1.0, 1.0), Rgb([255, 0, 0]));
+    }
+
+    #[test]
+    fn saturation_and_value() {
+        assert_eq!(hsv_to_rgb(0.3, 0.0, 0.5), Rgb([128, 128, 128]));
+        assert_eq!(hsv_to_rgb(0.7, 1.0, 0.0), Rgb([0, 0, 0]));
+        assert_eq!(hsv_to_rgb(0.0, 0.5, 1.0), Rgb([255, 128, 128]));
+    }
+}
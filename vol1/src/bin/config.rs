@@ -0,0 +1,674 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+use anymap::AnyMap;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{self, Map, Value};
+use toml;
+
+/// Where a configuration value came from. Later layers take precedence
+/// over earlier ones, no matter in which order they're loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Default,
+    File,
+    Env,
+    Cli,
+    /// Set while the program is running, see `Config::replace()`.
+    Runtime,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Layer::Default => "default",
+            Layer::File => "config file",
+            Layer::Env => "environment",
+            Layer::Cli => "command line",
+            Layer::Runtime => "runtime",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// `.json` files are JSON, everything else is assumed to be TOML.
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Format::Json,
+            _ => Format::Toml,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Format::Json => write!(f, "JSON"),
+            Format::Toml => write!(f, "TOML"),
+        }
+    }
+}
+
+/// A type that can be stored in a `Config`. The name is used as the key
+/// in config files, as `--name` on the command line and, uppercased, in
+/// environment variables. Values are read and written with serde, and
+/// the environment and command line values are deserialized from a string
+/// or, failing that, from JSON, so both `--host-address=localhost` and
+/// `--port=8000` work.
+pub trait Setting: Serialize + DeserializeOwned + 'static {
+    fn name() -> &'static str;
+
+    /// Checks constraints that the types alone can't express.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Syntax { format: Format, message: String },
+    /// `name` is the setting which couldn't be serialized, if it's known.
+    Serialize {
+        format: Format,
+        name: Option<&'static str>,
+        message: String,
+    },
+    UnknownKey { layer: Layer, key: String },
+    Invalid {
+        layer: Layer,
+        name: &'static str,
+        value: String,
+        reason: String,
+    },
+    Missing(Vec<&'static str>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref path, ref e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Syntax {
+                format,
+                ref message,
+            } => write!(f, "invalid {}: {}", format, message),
+            ConfigError::Serialize {
+                format,
+                name,
+                ref message,
+            } => match name {
+                Some(name) => write!(f, "cannot serialize {} as {}: {}", name, format, message),
+                None => write!(f, "cannot serialize the settings as {}: {}", format, message),
+            },
+            ConfigError::UnknownKey { layer, ref key } => {
+                write!(f, "unknown setting {:?} in {}", key, layer)
+            }
+            ConfigError::Invalid {
+                layer,
+                name,
+                ref value,
+                ref reason,
+            } => write!(f, "invalid {} {} in {}: {}", name, value, layer, reason),
+            ConfigError::Missing(ref names) => {
+                write!(f, "missing required settings: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        match *self {
+            ConfigError::Io(_, ref e) => e.description(),
+            ConfigError::Syntax { .. } => "config file syntax error",
+            ConfigError::Serialize { .. } => "cannot serialize setting",
+            ConfigError::UnknownKey { .. } => "unknown setting",
+            ConfigError::Invalid { .. } => "invalid setting",
+            ConfigError::Missing(_) => "missing required settings",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            ConfigError::Io(_, ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The type-erased part of a setting, so that values can be looked up,
+/// loaded and dumped by name.
+struct Registration {
+    name: &'static str,
+    type_id: TypeId,
+    required: bool,
+    load: fn(&mut Config, Value, Layer) -> Result<(), ConfigError>,
+    dump: fn(&Config) -> Option<Result<Value, ConfigError>>,
+}
+
+fn load<T: Setting>(config: &mut Config, value: Value, layer: Layer) -> Result<(), ConfigError> {
+    let invalid = |value: &Value, reason: String| {
+        ConfigError::Invalid {
+            layer: layer,
+            name: T::name(),
+            value: value.to_string(),
+            reason: reason,
+        }
+    };
+    let setting = match serde_json::from_value::<T>(value.clone()) {
+        Ok(setting) => setting,
+        Err(e) => return Err(invalid(&value, e.to_string())),
+    };
+    setting
+        .validate()
+        .map_err(|reason| invalid(&value, reason))?;
+    config.set(setting, layer);
+    Ok(())
+}
+
+fn dump<T: Setting>(config: &Config) -> Option<Result<Value, ConfigError>> {
+    config.get::<T>().map(|setting| {
+        serde_json::to_value(setting).map_err(|e| {
+            ConfigError::Serialize {
+                format: Format::Json,
+                name: Some(T::name()),
+                message: e.to_string(),
+            }
+        })
+    })
+}
+
+/// Called with the new value of a setting, or `None` if it was removed.
+type Listener = Box<FnMut(Option<&Any>)>;
+
+/// Typed configuration: at most one value of each `Setting` type, along
+/// with the layer which supplied it.
+pub struct Config {
+    values: AnyMap,
+    sources: HashMap<TypeId, Layer>,
+    registry: Vec<Registration>,
+    warnings: Vec<ConfigError>,
+    listeners: HashMap<TypeId, Vec<Listener>>,
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            config: Config {
+                values: AnyMap::new(),
+                sources: HashMap::new(),
+                registry: Vec::new(),
+                warnings: Vec::new(),
+                listeners: HashMap::new(),
+            },
+        }
+    }
+
+    pub fn get<T: Setting>(&self) -> Option<&T> {
+        self.values.get::<T>()
+    }
+
+    pub fn source<T: Setting>(&self) -> Option<Layer> {
+        self.sources.get(&TypeId::of::<T>()).cloned()
+    }
+
+    /// Problems that didn't stop the configuration from loading, such as
+    /// unknown keys in a config file.
+    pub fn warnings(&self) -> &[ConfigError] {
+        &self.warnings
+    }
+
+    /// Stores `value` unless a value from a higher layer is already there.
+    fn set<T: Setting>(&mut self, value: T, layer: Layer) {
+        let type_id = TypeId::of::<T>();
+        if self.sources.get(&type_id).map_or(true, |&source| source <= layer) {
+            self.values.insert(value);
+            self.sources.insert(type_id, layer);
+        }
+    }
+
+    /// Calls `listener` with the new value whenever a `T` is replaced at
+    /// runtime, including when a scoped override reverts it. If there was
+    /// no `T` before the override, the listener gets `None` instead.
+    pub fn subscribe<T, F>(&mut self, mut listener: F)
+    where
+        T: Setting,
+        F: FnMut(Option<&T>) + 'static,
+    {
+        let listener = move |value: Option<&Any>| {
+            listener(value.and_then(|value| value.downcast_ref::<T>()))
+        };
+        self.listeners
+            .entry(TypeId::of::<T>())
+            .or_insert_with(Vec::new)
+            .push(Box::new(listener));
+    }
+
+    fn notify<T: Setting>(&mut self) {
+        let value = self.values.get::<T>();
+        if let Some(listeners) = self.listeners.get_mut(&TypeId::of::<T>()) {
+            for listener in listeners.iter_mut() {
+                listener(value.map(|value| value as &Any));
+            }
+        }
+    }
+
+    /// Stores `value` on top of all the other layers and returns the
+    /// previous value, if any.
+    pub fn replace<T: Setting>(&mut self, value: T) -> Option<T> {
+        let previous = self.values.insert(value);
+        self.sources.insert(TypeId::of::<T>(), Layer::Runtime);
+        self.notify::<T>();
+        previous
+    }
+
+    /// Like `replace()`, but the previous value comes back when the
+    /// returned guard goes out of scope. In the meantime, the config is
+    /// available through the guard.
+    ///
+    /// Nested overrides taken through the guard are reverted first, but a
+    /// `replace()` of the same `T` through the guard is lost when the guard
+    /// is dropped.
+    pub fn scoped<T: Setting>(&mut self, value: T) -> ScopedOverride<T> {
+        let source = self.sources.get(&TypeId::of::<T>()).cloned();
+        let previous = self.replace(value).and_then(|previous| {
+            source.map(|source| (previous, source))
+        });
+        ScopedOverride {
+            config: self,
+            previous: previous,
+        }
+    }
+
+    /// The names and values of all registered settings which have a value.
+    fn values(&self) -> Result<Vec<(&'static str, Value)>, ConfigError> {
+        let mut values = Vec::new();
+        for registration in &self.registry {
+            if let Some(value) = (registration.dump)(self) {
+                values.push((registration.name, value?));
+            }
+        }
+        Ok(values)
+    }
+
+    /// All registered settings which have a value, keyed by name.
+    pub fn to_value(&self) -> Result<Value, ConfigError> {
+        let mut object = Map::new();
+        for (name, value) in self.values()? {
+            object.insert(name.to_string(), value);
+        }
+        Ok(Value::Object(object))
+    }
+
+    /// Serializes the settings, which can be loaded back with
+    /// `ConfigBuilder::load()` or `ConfigBuilder::file()`.
+    pub fn dump(&self, format: Format) -> Result<String, ConfigError> {
+        let serialize_error = |name: Option<&'static str>, message: String| {
+            ConfigError::Serialize {
+                format: format,
+                name: name,
+                message: message,
+            }
+        };
+        match format {
+            Format::Json => serde_json::to_string_pretty(&self.to_value()?)
+                .map_err(|e| serialize_error(None, e.to_string())),
+            Format::Toml => {
+                // TOML wants plain values before tables, which a JSON object
+                // doesn't care about, but a `toml::Value` takes care of.
+                let mut table = toml::value::Table::new();
+                for (name, value) in self.values()? {
+                    let value = toml::Value::try_from(value)
+                        .map_err(|e| serialize_error(Some(name), e.to_string()))?;
+                    table.insert(name.to_string(), value);
+                }
+                toml::to_string(&toml::Value::Table(table))
+                    .map_err(|e| serialize_error(None, e.to_string()))
+            }
+        }
+    }
+}
+
+pub struct ScopedOverride<'a, T: Setting> {
+    config: &'a mut Config,
+    previous: Option<(T, Layer)>,
+}
+
+impl<'a, T: Setting> Deref for ScopedOverride<'a, T> {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        self.config
+    }
+}
+
+impl<'a, T: Setting> DerefMut for ScopedOverride<'a, T> {
+    fn deref_mut(&mut self) -> &mut Config {
+        self.config
+    }
+}
+
+impl<'a, T: Setting> Drop for ScopedOverride<'a, T> {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some((value, source)) => {
+                self.config.values.insert(value);
+                self.config.sources.insert(TypeId::of::<T>(), source);
+                self.config.notify::<T>();
+            }
+            None => {
+                self.config.values.remove::<T>();
+                self.config.sources.remove(&TypeId::of::<T>());
+                self.config.notify::<T>();
+            }
+        }
+    }
+}
+
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    fn add<T: Setting>(mut self, required: bool) -> ConfigBuilder {
+        let type_id = TypeId::of::<T>();
+        self.config
+            .registry
+            .retain(|registration| registration.type_id != type_id);
+        self.config.registry.push(Registration {
+            name: T::name(),
+            type_id: type_id,
+            required: required,
+            load: load::<T>,
+            dump: dump::<T>,
+        });
+        self
+    }
+
+    /// Makes `T` known to the file, environment and command line layers.
+    pub fn register<T: Setting>(self) -> ConfigBuilder {
+        self.add::<T>(false)
+    }
+
+    /// Like `register`, but `build()` fails if no layer provides a `T`.
+    pub fn require<T: Setting>(self) -> ConfigBuilder {
+        self.add::<T>(true)
+    }
+
+    pub fn default<T: Setting>(mut self, value: T) -> ConfigBuilder {
+        self.config.set(value, Layer::Default);
+        self
+    }
+
+    fn registration(&self, name: &str) -> Option<&Registration> {
+        self.config
+            .registry
+            .iter()
+            .find(|registration| registration.name == name)
+    }
+
+    /// Loads a document such as the output of `Config::dump()`. Unknown
+    /// keys are recorded as warnings.
+    pub fn load(mut self, format: Format, text: &str) -> Result<ConfigBuilder, ConfigError> {
+        let syntax_error = |message: String| {
+            ConfigError::Syntax {
+                format: format,
+                message: message,
+            }
+        };
+        let value = match format {
+            Format::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+            Format::Toml => text.parse::<toml::Value>()
+                .map_err(|e| e.to_string())
+                .and_then(|value| serde_json::to_value(value).map_err(|e| e.to_string())),
+        };
+        let object = match value.map_err(&syntax_error)? {
+            Value::Object(object) => object,
+            _ => return Err(syntax_error("expected a table of settings".to_string())),
+        };
+        for (key, value) in object {
+            let load = match self.registration(&key) {
+                Some(registration) => registration.load,
+                None => {
+                    self.config.warnings.push(ConfigError::UnknownKey {
+                        layer: Layer::File,
+                        key: key,
+                    });
+                    continue;
+                }
+            };
+            load(&mut self.config, value, Layer::File)?;
+        }
+        Ok(self)
+    }
+
+    /// Loads a JSON or TOML file, depending on its extension. A missing
+    /// file is not an error, it simply doesn't provide any values.
+    pub fn file<P: AsRef<Path>>(self, path: P) -> Result<ConfigBuilder, ConfigError> {
+        let path = path.as_ref();
+        let mut contents = String::new();
+        match File::open(path).and_then(|mut file| file.read_to_string(&mut contents)) {
+            Ok(_) => self.load(Format::from_path(path), &contents),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(self),
+            Err(e) => Err(ConfigError::Io(path.to_path_buf(), e)),
+        }
+    }
+
+    fn parse(&mut self, name: &str, text: &str, layer: Layer) -> Result<(), ConfigError> {
+        let load = match self.registration(name) {
+            Some(registration) => registration.load,
+            None => {
+                return Err(ConfigError::UnknownKey {
+                    layer: layer,
+                    key: name.to_string(),
+                })
+            }
+        };
+        let value = Value::String(text.to_string());
+        let mut config_error = match load(&mut self.config, value, layer) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if let Ok(value) = serde_json::from_str(text) {
+            match load(&mut self.config, value, layer) {
+                Ok(()) => return Ok(()),
+                Err(e) => config_error = e,
+            }
+        }
+        Err(config_error)
+    }
+
+    /// Reads `PREFIX_NAME` environment variables, e.g. `DAY9_PORT`.
+    pub fn env(mut self, prefix: &str) -> Result<ConfigBuilder, ConfigError> {
+        let vars: Vec<_> = self.config
+            .registry
+            .iter()
+            .map(|registration| registration.name)
+            .filter_map(|name| {
+                let var = format!("{}{}", prefix, name.to_uppercase());
+                env::var(var).ok().map(|value| (name, value))
+            })
+            .collect();
+        for (name, value) in vars {
+            self.parse(name, &value, Layer::Env)?;
+        }
+        Ok(self)
+    }
+
+    /// Reads `--name=value` or `--name value` arguments.
+    pub fn args<I>(mut self, args: I) -> Result<ConfigBuilder, ConfigError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                return Err(ConfigError::UnknownKey {
+                    layer: Layer::Cli,
+                    key: arg,
+                });
+            }
+            let (name, value) = match arg.find('=') {
+                Some(index) => (arg[2..index].to_string(), arg[index + 1..].to_string()),
+                None => (arg[2..].to_string(), args.next().unwrap_or_default()),
+            };
+            self.parse(&name.replace('-', "_"), &value, Layer::Cli)?;
+        }
+        Ok(self)
+    }
+
+    pub fn build(self) -> Result<Config, ConfigError> {
+        let missing: Vec<_> = self.config
+            .registry
+            .iter()
+            .filter(|registration| {
+                registration.required && !self.config.sources.contains_key(&registration.type_id)
+            })
+            .map(|registration| registration.name)
+            .collect();
+        if missing.is_empty() {
+            Ok(self.config)
+        } else {
+            Err(ConfigError::Missing(missing))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Timeout(u32);
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Fallback(Option<u32>);
+
+    impl Setting for Server {
+        fn name() -> &'static str {
+            "server"
+        }
+    }
+
+    impl Setting for Timeout {
+        fn name() -> &'static str {
+            "timeout"
+        }
+    }
+
+    impl Setting for Fallback {
+        fn name() -> &'static str {
+            "fallback"
+        }
+    }
+
+    fn config() -> Config {
+        Config::builder()
+            .register::<Server>()
+            .register::<Timeout>()
+            .default(Server {
+                host: "localhost".to_string(),
+                port: 8000,
+            })
+            .default(Timeout(30))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn toml_tables_come_last() {
+        let dumped = config().dump(Format::Toml).unwrap();
+        assert_eq!(dumped, "timeout = 30\n\n[server]\nhost = \"localhost\"\nport = 8000\n");
+    }
+
+    #[test]
+    fn dump_and_load() {
+        for &format in &[Format::Json, Format::Toml] {
+            let dumped = config().dump(format).unwrap();
+            let loaded = Config::builder()
+                .register::<Server>()
+                .register::<Timeout>()
+                .load(format, &dumped)
+                .unwrap()
+                .build()
+                .unwrap();
+            assert_eq!(loaded.get::<Server>(), config().get::<Server>());
+            assert_eq!(loaded.get::<Timeout>(), Some(&Timeout(30)));
+            assert_eq!(loaded.source::<Timeout>(), Some(Layer::File));
+        }
+    }
+
+    #[test]
+    fn listeners_see_scoped_removal() {
+        let mut config = Config::builder().register::<Timeout>().build().unwrap();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        config.subscribe(move |timeout: Option<&Timeout>| {
+            log.borrow_mut().push(timeout.map(|timeout| timeout.0))
+        });
+        {
+            let overridden = config.scoped(Timeout(5));
+            assert_eq!(overridden.get::<Timeout>(), Some(&Timeout(5)));
+        }
+        assert_eq!(config.get::<Timeout>(), None);
+        assert_eq!(config.source::<Timeout>(), None);
+        assert_eq!(*seen.borrow(), vec![Some(5), None]);
+    }
+
+    #[test]
+    fn replace_through_guard_is_reverted() {
+        let mut config = config();
+        {
+            let mut overridden = config.scoped(Timeout(5));
+            overridden.replace(Timeout(10));
+            {
+                let nested = overridden.scoped(Timeout(15));
+                assert_eq!(nested.get::<Timeout>(), Some(&Timeout(15)));
+            }
+            assert_eq!(overridden.get::<Timeout>(), Some(&Timeout(10)));
+        }
+        assert_eq!(config.get::<Timeout>(), Some(&Timeout(30)));
+        assert_eq!(config.source::<Timeout>(), Some(Layer::Default));
+    }
+
+    #[test]
+    fn toml_has_no_null() {
+        let config = Config::builder()
+            .register::<Fallback>()
+            .default(Fallback(None))
+            .build()
+            .unwrap();
+        assert!(config.dump(Format::Json).is_ok());
+        match config.dump(Format::Toml) {
+            Err(ConfigError::Serialize { format, name, .. }) => {
+                assert_eq!(format, Format::Toml);
+                assert_eq!(name, Some("fallback"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
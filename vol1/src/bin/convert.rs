@@ -0,0 +1,589 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use csv::{self, Reader, Writer};
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{self, Json, ToJson};
+use yaml_rust::{EmitError, ScanError, Yaml, YamlEmitter, YamlLoader};
+use yaml_rust::yaml::Hash;
+
+use super::Movie;
+use typed_csv::{decode_by_header, FromRecord, ReadError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Format> {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Some(Format::Csv),
+            Some("json") => Some(Format::Json),
+            Some("yml") | Some("yaml") => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// What the rows are expected to look like: either `Movie` records with
+/// typed fields, or arbitrary string-keyed rows taken as they are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schema {
+    Movie,
+    Generic,
+}
+
+#[derive(Debug)]
+pub enum ConvertError {
+    Csv(csv::Error),
+    Read(ReadError),
+    Json(String),
+    Yaml(String),
+    Shape(String),
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConvertError::Csv(ref e) => write!(f, "CSV error: {}", e),
+            ConvertError::Read(ref e) => write!(f, "{}", e),
+            ConvertError::Json(ref e) => write!(f, "JSON error: {}", e),
+            ConvertError::Yaml(ref e) => write!(f, "YAML error: {}", e),
+            ConvertError::Shape(ref e) => write!(f, "unexpected data: {}", e),
+        }
+    }
+}
+
+impl Error for ConvertError {
+    fn description(&self) -> &str {
+        match *self {
+            ConvertError::Csv(ref e) => e.description(),
+            ConvertError::Read(ref e) => e.description(),
+            ConvertError::Json(_) => "JSON error",
+            ConvertError::Yaml(_) => "YAML error",
+            ConvertError::Shape(_) => "unexpected data",
+        }
+    }
+}
+
+impl From<csv::Error> for ConvertError {
+    fn from(e: csv::Error) -> ConvertError {
+        ConvertError::Csv(e)
+    }
+}
+
+impl From<ReadError> for ConvertError {
+    fn from(e: ReadError) -> ConvertError {
+        ConvertError::Read(e)
+    }
+}
+
+impl From<json::ParserError> for ConvertError {
+    fn from(e: json::ParserError) -> ConvertError {
+        ConvertError::Json(e.to_string())
+    }
+}
+
+impl From<ScanError> for ConvertError {
+    fn from(e: ScanError) -> ConvertError {
+        ConvertError::Yaml(e.to_string())
+    }
+}
+
+impl From<EmitError> for ConvertError {
+    fn from(e: EmitError) -> ConvertError {
+        ConvertError::Yaml(e.to_string())
+    }
+}
+
+pub struct Conversion {
+    pub output: String,
+    /// Everything that didn't survive the conversion unchanged.
+    pub warnings: Vec<String>,
+}
+
+impl ToJson for Movie {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("title".to_string(), self.title.to_json());
+        object.insert("bad_guy".to_string(), self.bad_guy.to_json());
+        object.insert("pub_year".to_string(), self.pub_year.to_json());
+        Json::Object(object)
+    }
+}
+
+struct Table {
+    columns: Vec<String>,
+    rows: Vec<json::Object>,
+}
+
+impl Table {
+    fn new() -> Table {
+        Table {
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, row: json::Object) {
+        for key in row.keys() {
+            if !self.columns.contains(key) {
+                self.columns.push(key.clone());
+            }
+        }
+        self.rows.push(row);
+    }
+}
+
+impl ToJson for Table {
+    fn to_json(&self) -> Json {
+        Json::Array(self.rows.iter().cloned().map(Json::Object).collect())
+    }
+}
+
+fn read_csv(input: &str) -> Result<Table, ConvertError> {
+    let mut reader = Reader::from_string(input);
+    let columns = reader.headers()?;
+    let mut table = Table::new();
+    for record in reader.records() {
+        let record = record?;
+        let row = columns
+            .iter()
+            .cloned()
+            .zip(record.into_iter().map(Json::String))
+            .collect();
+        table.rows.push(row);
+    }
+    table.columns = columns;
+    Ok(table)
+}
+
+fn read_json(input: &str) -> Result<Table, ConvertError> {
+    let items = match Json::from_str(input)? {
+        Json::Array(items) => items,
+        _ => {
+            return Err(ConvertError::Shape(
+                "expected an array of objects".to_string(),
+            ))
+        }
+    };
+    let mut table = Table::new();
+    for (i, item) in items.into_iter().enumerate() {
+        match item {
+            Json::Object(row) => table.push(row),
+            _ => {
+                return Err(ConvertError::Shape(format!(
+                    "item {} is not an object",
+                    i + 1
+                )))
+            }
+        }
+    }
+    Ok(table)
+}
+
+fn yaml_key(key: Yaml, path: &str, warnings: &mut Vec<String>) -> Result<String, ConvertError> {
+    let key = match key {
+        Yaml::String(key) => return Ok(key),
+        Yaml::Integer(i) => i.to_string(),
+        Yaml::Real(s) => s,
+        Yaml::Boolean(b) => b.to_string(),
+        Yaml::Null => "null".to_string(),
+        _ => return Err(ConvertError::Shape(format!("{}: unsupported key", path))),
+    };
+    warnings.push(format!("{}: key {} was converted to a string", path, key));
+    Ok(key)
+}
+
+fn yaml_to_json(yaml: Yaml, path: &str, warnings: &mut Vec<String>) -> Result<Json, ConvertError> {
+    Ok(match yaml {
+        Yaml::Null => Json::Null,
+        Yaml::Boolean(b) => Json::Boolean(b),
+        Yaml::Integer(i) if i < 0 => Json::I64(i),
+        Yaml::Integer(i) => Json::U64(i as u64),
+        Yaml::Real(s) => match s.parse::<f64>() {
+            Ok(f) if f.is_finite() => Json::F64(f),
+            _ => {
+                warnings.push(format!("{}: {} can't be represented, using null", path, s));
+                Json::Null
+            }
+        },
+        Yaml::String(s) => Json::String(s),
+        Yaml::Array(items) => {
+            let mut array = Vec::with_capacity(items.len());
+            for (i, item) in items.into_iter().enumerate() {
+                array.push(yaml_to_json(item, &format!("{}[{}]", path, i), warnings)?);
+            }
+            Json::Array(array)
+        }
+        Yaml::Hash(hash) => {
+            let mut object = BTreeMap::new();
+            for (key, value) in hash {
+                let key = yaml_key(key, path, warnings)?;
+                let value = yaml_to_json(value, &format!("{}.{}", path, key), warnings)?;
+                object.insert(key, value);
+            }
+            Json::Object(object)
+        }
+        Yaml::Alias(_) | Yaml::BadValue => {
+            return Err(ConvertError::Shape(format!("{}: invalid value", path)))
+        }
+    })
+}
+
+fn read_yaml(input: &str, warnings: &mut Vec<String>) -> Result<Table, ConvertError> {
+    let document = YamlLoader::load_from_str(input)?.into_iter().next();
+    let items = match document {
+        Some(Yaml::Array(items)) => items,
+        _ => {
+            return Err(ConvertError::Shape(
+                "expected a list of mappings".to_string(),
+            ))
+        }
+    };
+    let mut table = Table::new();
+    for (i, item) in items.into_iter().enumerate() {
+        match yaml_to_json(item, &format!("item {}", i + 1), warnings)? {
+            Json::Object(row) => table.push(row),
+            _ => {
+                return Err(ConvertError::Shape(format!(
+                    "item {} is not a mapping",
+                    i + 1
+                )))
+            }
+        }
+    }
+    Ok(table)
+}
+
+fn read_table(
+    input: &str,
+    from: Format,
+    warnings: &mut Vec<String>,
+) -> Result<Table, ConvertError> {
+    match from {
+        Format::Csv => read_csv(input),
+        Format::Json => read_json(input),
+        Format::Yaml => read_yaml(input, warnings),
+    }
+}
+
+fn warn_about_extra_columns(columns: &[String], warnings: &mut Vec<String>) {
+    for column in columns {
+        if !Movie::columns().contains(&column.as_str()) {
+            warnings.push(format!(
+                "column '{}' is not part of Movie, dropping it",
+                column
+            ));
+        }
+    }
+}
+
+fn read_movies(
+    input: &str,
+    from: Format,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Movie>, ConvertError> {
+    if from == Format::Csv {
+        let mut reader = Reader::from_string(input);
+        warn_about_extra_columns(&reader.headers()?, warnings);
+        let movies = decode_by_header::<_, Movie>(&mut reader)?;
+        return movies
+            .map(|movie| movie.map_err(ConvertError::from))
+            .collect();
+    }
+    let table = read_table(input, from, warnings)?;
+    warn_about_extra_columns(&table.columns, warnings);
+    let mut movies = Vec::with_capacity(table.rows.len());
+    for (i, row) in table.rows.into_iter().enumerate() {
+        let mut decoder = json::Decoder::new(Json::Object(row));
+        let movie = Movie::decode(&mut decoder).map_err(|e| {
+            ConvertError::Shape(format!("item {} is not a valid movie: {}", i + 1, e))
+        })?;
+        movies.push(movie);
+    }
+    Ok(movies)
+}
+
+fn movies_to_table(movies: &[Movie]) -> Table {
+    Table {
+        columns: Movie::columns().iter().map(|c| c.to_string()).collect(),
+        rows: movies
+            .iter()
+            .map(|movie| match movie.to_json() {
+                Json::Object(row) => row,
+                _ => unreachable!(),
+            })
+            .collect(),
+    }
+}
+
+fn write_csv(
+    table: &Table,
+    typed: bool,
+    warnings: &mut Vec<String>,
+) -> Result<String, ConvertError> {
+    let mut writer = Writer::from_memory();
+    writer.encode(&table.columns)?;
+    let mut untyped_columns = Vec::new();
+    for (i, row) in table.rows.iter().enumerate() {
+        let mut fields = Vec::with_capacity(table.columns.len());
+        for column in &table.columns {
+            let field = match row.get(column) {
+                None => {
+                    warnings.push(format!(
+                        "row {}: no value for '{}', leaving it empty",
+                        i + 1,
+                        column
+                    ));
+                    String::new()
+                }
+                Some(&Json::Null) => {
+                    warnings.push(format!(
+                        "row {}: '{}' is null, leaving it empty",
+                        i + 1,
+                        column
+                    ));
+                    String::new()
+                }
+                Some(&Json::String(ref s)) => s.clone(),
+                Some(value @ &Json::Array(_)) | Some(value @ &Json::Object(_)) => {
+                    warnings.push(format!(
+                        "row {}: '{}' is nested, writing it as JSON text",
+                        i + 1,
+                        column
+                    ));
+                    value.to_string()
+                }
+                Some(value) => {
+                    if !typed && !untyped_columns.contains(column) {
+                        untyped_columns.push(column.clone());
+                        warnings.push(format!(
+                            "column '{}': numbers and booleans are written as text",
+                            column
+                        ));
+                    }
+                    value.to_string()
+                }
+            };
+            fields.push(field);
+        }
+        writer.encode(fields)?;
+    }
+    Ok(writer.as_string().to_string())
+}
+
+fn json_to_yaml(value: &Json, path: &str, warnings: &mut Vec<String>) -> Yaml {
+    match *value {
+        Json::Null => Yaml::Null,
+        Json::Boolean(b) => Yaml::Boolean(b),
+        Json::I64(i) => Yaml::Integer(i),
+        Json::U64(u) if u <= i64::max_value() as u64 => Yaml::Integer(u as i64),
+        Json::U64(u) => {
+            warnings.push(format!(
+                "{}: {} is too big for a YAML integer, using a float",
+                path, u
+            ));
+            Yaml::Real(format!("{:?}", u as f64))
+        }
+        Json::F64(f) => Yaml::Real(format!("{:?}", f)),
+        Json::String(ref s) => Yaml::String(s.clone()),
+        Json::Array(ref items) => Yaml::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| json_to_yaml(item, &format!("{}[{}]", path, i), warnings))
+                .collect(),
+        ),
+        Json::Object(ref object) => {
+            let mut hash = Hash::new();
+            for (key, value) in object {
+                let value = json_to_yaml(value, &format!("{}.{}", path, key), warnings);
+                hash.insert(Yaml::String(key.clone()), value);
+            }
+            Yaml::Hash(hash)
+        }
+    }
+}
+
+fn write_yaml(table: &Table, warnings: &mut Vec<String>) -> Result<String, ConvertError> {
+    let mut items = Vec::with_capacity(table.rows.len());
+    for (i, row) in table.rows.iter().enumerate() {
+        let item = Json::Object(row.clone());
+        items.push(json_to_yaml(&item, &format!("item {}", i + 1), warnings));
+    }
+    let yaml = Yaml::Array(items);
+    let mut output = String::new();
+    YamlEmitter::new(&mut output).dump(&yaml)?;
+    output.push('\n');
+    Ok(output)
+}
+
+pub fn convert(
+    input: &str,
+    from: Format,
+    to: Format,
+    schema: Schema,
+) -> Result<Conversion, ConvertError> {
+    let mut warnings = Vec::new();
+    let table = match schema {
+        Schema::Movie => movies_to_table(&read_movies(input, from, &mut warnings)?),
+        Schema::Generic => {
+            if from == Format::Csv && to != Format::Csv {
+                warnings.push("CSV has no types, all values are kept as strings".to_string());
+            }
+            read_table(input, from, &mut warnings)?
+        }
+    };
+    let output = match to {
+        Format::Csv => write_csv(&table, schema == Schema::Movie, &mut warnings)?,
+        Format::Json => format!("{}\n", json::as_pretty_json(&table.to_json())),
+        Format::Yaml => write_yaml(&table, &mut warnings)?,
+    };
+    Ok(Conversion {
+        output: output,
+        warnings: warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WESTERNS: &'static str = "title,bad_guy,pub_year\n\
+                                    \"The Good, the Bad and the Ugly\",Tuco,1966\n\
+                                    Hang 'Em High,Wilson,1968\n";
+
+    fn convert_quietly(input: &str, from: Format, to: Format, schema: Schema) -> String {
+        let conversion = convert(input, from, to, schema).unwrap();
+        assert!(conversion.warnings.is_empty(), "{:?}", conversion.warnings);
+        conversion.output
+    }
+
+    fn warnings(input: &str, from: Format, to: Format, schema: Schema) -> Vec<String> {
+        convert(input, from, to, schema).unwrap().warnings
+    }
+
+    #[test]
+    fn movies_round_trip() {
+        let json = convert_quietly(WESTERNS, Format::Csv, Format::Json, Schema::Movie);
+        let yaml = convert_quietly(&json, Format::Json, Format::Yaml, Schema::Movie);
+        let csv = convert_quietly(&yaml, Format::Yaml, Format::Csv, Schema::Movie);
+        assert_eq!(csv, WESTERNS);
+        let movies = Json::from_str(&json).unwrap();
+        assert_eq!(movies[1]["pub_year"], Json::U64(1968));
+    }
+
+    #[test]
+    fn generic_json_and_yaml_round_trip() {
+        let json = r#"[{"name": "Tuco", "bounty": 2000, "ratio": 0.5,
+                        "aliases": ["The Rat"], "alive": true, "horse": null}]"#;
+        let yaml = convert_quietly(json, Format::Json, Format::Yaml, Schema::Generic);
+        let back = convert_quietly(&yaml, Format::Yaml, Format::Json, Schema::Generic);
+        assert_eq!(
+            Json::from_str(&back).unwrap(),
+            Json::from_str(json).unwrap()
+        );
+    }
+
+    #[test]
+    fn csv_has_no_types() {
+        let warnings = warnings(WESTERNS, Format::Csv, Format::Json, Schema::Generic);
+        assert_eq!(
+            warnings,
+            vec!["CSV has no types, all values are kept as strings"]
+        );
+        let json = convert(WESTERNS, Format::Csv, Format::Json, Schema::Generic).unwrap();
+        let movies = Json::from_str(&json.output).unwrap();
+        assert_eq!(movies[1]["pub_year"], Json::String("1968".to_string()));
+    }
+
+    #[test]
+    fn lossy_csv_output() {
+        let json = r#"[{"title": "Rio Bravo", "pub_year": 1959, "cast": ["John Wayne"]},
+                       {"title": null, "remake": false}]"#;
+        assert_eq!(
+            warnings(json, Format::Json, Format::Csv, Schema::Generic),
+            vec![
+                "row 1: 'cast' is nested, writing it as JSON text",
+                "column 'pub_year': numbers and booleans are written as text",
+                "row 1: no value for 'remake', leaving it empty",
+                "row 2: no value for 'cast', leaving it empty",
+                "row 2: no value for 'pub_year', leaving it empty",
+                "row 2: 'title' is null, leaving it empty",
+                "column 'remake': numbers and booleans are written as text",
+            ]
+        );
+    }
+
+    #[test]
+    fn lossy_yaml_numbers_and_keys() {
+        let json = r#"[{"reward": 18446744073709551615}]"#;
+        assert_eq!(
+            warnings(json, Format::Json, Format::Yaml, Schema::Generic),
+            vec![
+                "item 1.reward: 18446744073709551615 is too big for a YAML integer, using a float",
+            ]
+        );
+        let yaml = "- 1: one\n  ratio: .inf\n";
+        assert_eq!(
+            warnings(yaml, Format::Yaml, Format::Json, Schema::Generic),
+            vec![
+                "item 1: key 1 was converted to a string",
+                "item 1.ratio: .inf can't be represented, using null",
+            ]
+        );
+    }
+
+    #[test]
+    fn extra_columns_are_dropped() {
+        let json = r#"[{"title": "The Magnificent Seven", "bad_guy": "Calvera",
+                        "pub_year": 1960, "remake_of": "Seven Samurai"}]"#;
+        let conversion = convert(json, Format::Json, Format::Csv, Schema::Movie).unwrap();
+        assert_eq!(
+            conversion.output,
+            "title,bad_guy,pub_year\nThe Magnificent Seven,Calvera,1960\n"
+        );
+        assert_eq!(
+            conversion.warnings,
+            vec!["column 'remake_of' is not part of Movie, dropping it"]
+        );
+    }
+
+    #[test]
+    fn unexpected_shapes() {
+        let errors = vec![
+            convert("{}", Format::Json, Format::Csv, Schema::Generic),
+            convert("[1]", Format::Json, Format::Csv, Schema::Generic),
+            convert("a: b", Format::Yaml, Format::Csv, Schema::Generic),
+            convert(
+                r#"[{"title": "Hombre"}]"#,
+                Format::Json,
+                Format::Csv,
+                Schema::Movie,
+            ),
+        ];
+        let messages = errors
+            .into_iter()
+            .map(|result| match result {
+                Err(e @ ConvertError::Shape(_)) => e.to_string(),
+                Err(e) => panic!("unexpected error: {}", e),
+                Ok(_) => panic!("converted successfully"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "unexpected data: expected an array of objects",
+                "unexpected data: item 1 is not an object",
+                "unexpected data: expected a list of mappings",
+                "unexpected data: item 1 is not a valid movie: MissingFieldError(\"bad_guy\")",
+            ]
+        );
+    }
+}
@@ -1,8 +1,15 @@
+extern crate image;
 extern crate num;
 extern crate tau;
 
 use num::complex::{Complex, Complex64};
 
+mod angle;
+mod complex;
+
+use angle::Angle;
+use complex::{Polynomial, Viewport};
+
 fn main() {
     println!("24 days of Rust - tau (day 10)");
     println!("τ = {}", tau::TAU);
@@ -16,4 +23,37 @@ fn main() {
         tau::TAU.cos()
     );
     println!("That other constant = {}", tau::TAU / 2.0);
+    let right = Angle::from_degrees(90.0);
+    println!(
+        "{} = {} rad = {} turns = {} gradians",
+        right,
+        right.to_radians(),
+        right.to_turns(),
+        right.to_gradians()
+    );
+    let heading = Angle::from_degrees(350.0).wrapping_add(Angle::from_degrees(20.0));
+    println!("350° + 20° = {:.1}", heading);
+    println!("-90° normalized: {:.1}", (-right).normalized());
+    println!("270° normalized around zero: {:.1}", (right * 3.0).normalized_signed());
+    println!(
+        "From 350° to 10° is {:.1}",
+        Angle::from_degrees(350.0).difference(Angle::from_degrees(10.0))
+    );
+    println!("sin(τ/12) = {:.3}", Angle::from_turns(1.0 / 12.0).sin());
+    for root in complex::roots_of_unity(6) {
+        println!("{:.3} to the 6th power is {:.3}", root, num::pow(root, 6));
+    }
+    // z^3 - 2z + 2
+    let p = Polynomial::real(&[1.0, 0.0, -2.0, 2.0]);
+    match p.roots() {
+        Ok(roots) => for root in roots {
+            println!("p({:.6}) = {:.3}", root, p.eval(root));
+        },
+        Err(e) => println!("Cannot find the roots: {}", e),
+    }
+    let f = |z: Complex64| (z * z - 1.0) / (z * z + Complex::i());
+    match complex::save_domain_coloring(f, Viewport::square(2.0), 512, 512, "out_domain.png") {
+        Ok(_) => println!("Saved domain coloring of (z^2 - 1) / (z^2 + i) to out_domain.png"),
+        Err(e) => println!("Cannot save the image: {}", e),
+    }
 }
@@ -18,6 +18,10 @@ use rustc_serialize::json::Json;
 
 use time::Timespec;
 
+mod blog;
+
+use blog::{BlogError, BlogRepository};
+
 fn get_single_value<T>(conn: &Connection, query: &str) -> PgResult<T>
 where
     T: FromSql,
@@ -29,6 +33,39 @@ where
     row.get_opt(0).unwrap()
 }
 
+fn first_posts(repo: &BlogRepository) -> Result<(), BlogError> {
+    repo.create_table()?;
+    for i in 1..5 {
+        let title = format!("Blogpost number {}", i);
+        let text = format!("Content of the blogpost #{}", i);
+        repo.create(&title, &text)?;
+    }
+    for post in repo.list(1, 2)? {
+        println!("ID={}, title={}", post.id, post.title);
+    }
+    Ok(())
+}
+
+fn repository_demo(repo: &BlogRepository) -> Result<(), BlogError> {
+    let mut post = repo.create("Hello from the repository", "Typed rows and proper errors")?;
+    println!("Created {:?}", post);
+    post.body = "Typed rows, proper errors and full-text search".to_string();
+    repo.update(&post)?;
+    println!("Updated {:?}", repo.get(post.id)?);
+    println!("{} posts in total", repo.count()?);
+    for (number, post) in repo.list(1, 3)?.iter().enumerate() {
+        println!("{}. {}", number + 1, post.title);
+    }
+    for post in repo.search("searching")? {
+        println!("Found ID={}: {}", post.id, post.body);
+    }
+    repo.delete(post.id)?;
+    if let Err(e) = repo.get(post.id) {
+        println!("After deleting: {}", e);
+    }
+    Ok(())
+}
+
 #[cfg(target_family = "unix")]
 fn sql_macro() {
     //let query = sql!("select '{4, 5, 6}'::int[]");
@@ -50,41 +87,9 @@ fn main() {
             return;
         }
     };
-    conn.execute(
-        "create table if not exists blog (
-        id serial primary key,
-        title \
-                  varchar(255),
-        body text)",
-        &[],
-    ).expect("Table creation failed");
-    let stmt = match conn.prepare("insert into blog (title, body) values ($1, $2)") {
-        Ok(stmt) => stmt,
-        Err(e) => {
-            println!("Preparing query failed: {:?}", e);
-            return;
-        }
-    };
-    for i in 1..5 {
-        let title = format!("Blogpost number {}", i);
-        let text = format!("Content of the blogpost #{}", i);
-        stmt.execute(&[&title, &text]).expect(
-            "Inserting blogposts failed",
-        );
-    }
-    let stmt = match conn.prepare("select id, title, body from blog where id < $1") {
-        Ok(stmt) => stmt,
-        Err(e) => {
-            println!("Preparing query failed: {:?}", e);
-            return;
-        }
-    };
-    let max_id: i32 = 3;
-    let rows = stmt.query(&[&max_id]).expect("Selecting blogposts failed");
-    for row in rows.iter() {
-        let id: i32 = row.get("id");
-        let title: String = row.get("title");
-        println!("ID={}, title={}", id, title);
+    let repo = BlogRepository::new(&conn);
+    if let Err(e) = first_posts(&repo) {
+        println!("Repository error: {}", e);
     }
     println!("{:?}", get_single_value::<bool>(&conn, "select 1=1"));
     println!("{:?}", get_single_value::<i32>(&conn, "select 1=1"));
@@ -106,4 +111,8 @@ fn main() {
     // println!("{:?}", ts_range);
 
     sql_macro();
+
+    if let Err(e) = repository_demo(&repo) {
+        println!("Repository error: {}", e);
+    }
 }
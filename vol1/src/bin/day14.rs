@@ -1,12 +1,19 @@
 extern crate image;
 extern crate nalgebra;
+extern crate tau;
 
-use std::f64::consts::{PI, FRAC_PI_2};
+use std::f64::consts::PI;
 use std::fs::File;
 use std::path::Path;
 use image::{GenericImage, Pixel, Rgba};
 use nalgebra::{DVector, Matrix2, Point2, Rotation2, Vector2, Vector3};
 
+// Only a couple of the conversions are needed here.
+#[allow(dead_code)]
+mod angle;
+
+use angle::Angle;
+
 fn draw(v: &DVector<f64>, path: &Path) {
     let width = v.len() as u32;
     let height = 128u32;
@@ -32,9 +39,10 @@ fn main() {
     // -1, 0
     let rot = Matrix2::new(0.0f64, -1.0, 1.0, 0.0);
     println!("{}", rot * v);
-    let angle = FRAC_PI_2;
-    let rot = Rotation2::new(angle);
+    let angle = Angle::from_degrees(90.0);
+    let rot: Rotation2<f64> = Rotation2::new(angle.into());
     println!("{}", rot * v);
+    println!("Rotation by {}", Angle::from_radians(rot.angle()));
     let point = Point2::new(4.0f64, 4.0);
     println!("Translate from {} to {}", point, point + v);
 
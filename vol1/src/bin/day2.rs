@@ -1,14 +1,26 @@
+extern crate docopt;
 extern crate primal;
+extern crate rustc_serialize;
 
+use docopt::{ArgvMap, Docopt};
 use primal::Sieve;
+use rustc_serialize::json;
+
+mod arithmetic;
+mod factorization;
+mod segmented;
+
+use arithmetic::{carmichael, divisors, moebius, sum_of_divisors, totient};
+use factorization::{factor_u64, is_prime_u64};
+use segmented::SegmentedSieve;
 
 fn num_divisors(n: usize, primes: &Sieve) -> Option<usize> {
-    primes.factor(n)
+    factor_u64(n as u64, primes)
         .map(|factors| factors.into_iter().map(|(_, x)| x + 1).product())
         .ok()
 }
 
-fn main() {
+fn demo() {
     println!("24 days of Rust - primal (day 2)");
     let sieve = Sieve::new(10000);
     let suspect = 5273;
@@ -22,4 +34,292 @@ fn main() {
     }
     println!("{:?}", sieve.factor(2610));
     println!("{:?}", num_divisors(2610, &sieve));
+    println!("σ(2610) = {:?}", sum_of_divisors(2610, &sieve));
+    println!("φ(2610) = {:?}", totient(2610, &sieve));
+    println!("μ(2610) = {:?}", moebius(2610, &sieve));
+    println!("λ(2610) = {:?}", carmichael(2610, &sieve));
+    println!("divisors of 360: {:?}", divisors(360, &sieve));
+    let n = 1_000_000;
+    let (_, high) = primal::estimate_nth_prime(n as u64);
+    let segmented = SegmentedSieve::new(high as usize);
+    match segmented.primes_from(0).nth(n - 1) {
+        Some(number) => println!("{}th prime is {}", n, number),
+        None => println!("I don't know anything about {}th prime.", n),
+    }
+    let lo = 1_000_000_000_000;
+    let hi = lo + 100_000;
+    let segmented = SegmentedSieve::new(hi);
+    println!("{} is prime: {}", lo + 39, segmented.is_prime(lo + 39));
+    println!(
+        "There are {} primes between {} and {}",
+        segmented.primes_in(lo, hi).count(),
+        lo,
+        hi
+    );
+    let huge = 18_446_744_073_709_551_557;
+    println!("{} is prime: {}", huge, is_prime_u64(huge, &sieve));
+    println!("{:?}", factor_u64(600_851_475_143, &sieve));
+    println!("{:?}", num_divisors(1_000_000_016_000_000_063, &sieve));
+}
+
+const USAGE: &'static str = "
+Usage:
+    day2
+    day2 is-prime <n> [--json]
+    day2 nth <n> [--json]
+    day2 factor <number>... [--json]
+    day2 divisors <n> [--json]
+    day2 range <a> <b> [--count] [--json]
+    day2 twin-primes <a> <b> [--json]
+    day2 (-h | --help)
+
+Ranges include <a> and exclude <b>. Without a command, runs the demo.
+
+Options:
+    --count     print only how many primes there are
+    --json      print results as JSON
+    -h, --help  display this help and exit
+";
+
+#[derive(RustcEncodable)]
+struct Primality {
+    n: u64,
+    is_prime: bool,
+}
+
+#[derive(RustcEncodable)]
+struct NthPrime {
+    n: usize,
+    prime: usize,
+}
+
+#[derive(RustcEncodable)]
+struct Factorization {
+    n: u64,
+    factors: Vec<(usize, usize)>,
+}
+
+#[derive(RustcEncodable)]
+struct Divisors {
+    n: u64,
+    divisors: Vec<usize>,
+}
+
+#[derive(RustcEncodable)]
+struct PrimeCount {
+    from: usize,
+    to: usize,
+    count: usize,
+}
+
+#[derive(RustcEncodable)]
+struct PrimeRange {
+    from: usize,
+    to: usize,
+    primes: Vec<usize>,
+}
+
+#[derive(RustcEncodable)]
+struct TwinPrimes {
+    from: usize,
+    to: usize,
+    pairs: Vec<(usize, usize)>,
+}
+
+fn parse_number(args: &ArgvMap, key: &str) -> Result<u64, String> {
+    let value = args.get_str(key);
+    value.parse().map_err(
+        |_| format!("{} is not a valid number", value),
+    )
+}
+
+fn parse_range(args: &ArgvMap) -> Result<(usize, usize), String> {
+    let from = parse_number(args, "<a>")? as usize;
+    let to = parse_number(args, "<b>")? as usize;
+    if from > to {
+        return Err(format!("{} is greater than {}", from, to));
+    }
+    Ok((from, to))
+}
+
+fn is_prime_command(n: u64, as_json: bool) -> Result<(), String> {
+    let sieve = Sieve::new(10000);
+    let result = Primality {
+        n: n,
+        is_prime: is_prime_u64(n, &sieve),
+    };
+    if as_json {
+        println!("{}", json::as_json(&result));
+    } else {
+        println!("{} is prime: {}", result.n, result.is_prime);
+    }
+    Ok(())
+}
+
+fn nth_command(n: u64, as_json: bool) -> Result<(), String> {
+    if n == 0 {
+        return Err("primes are counted from 1".to_string());
+    }
+    let (_, high) = primal::estimate_nth_prime(n);
+    let sieve = SegmentedSieve::new(high as usize);
+    let n = n as usize;
+    let result = match sieve.primes_from(0).nth(n - 1) {
+        Some(prime) => NthPrime { n: n, prime: prime },
+        None => {
+            return Err(format!(
+                "I don't know anything about {}th prime, it's beyond {}.",
+                n,
+                sieve.upper_bound()
+            ))
+        }
+    };
+    if as_json {
+        println!("{}", json::as_json(&result));
+    } else {
+        println!("{}th prime is {}", result.n, result.prime);
+    }
+    Ok(())
+}
+
+fn factor_command(numbers: &[u64], as_json: bool) -> Result<(), String> {
+    let sieve = Sieve::new(10000);
+    let mut results = Vec::with_capacity(numbers.len());
+    for &n in numbers {
+        let factors = factor_u64(n, &sieve).map_err(
+            |_| format!("{} can't be factored", n),
+        )?;
+        results.push(Factorization {
+            n: n,
+            factors: factors,
+        });
+    }
+    if as_json {
+        println!("{}", json::as_json(&results));
+    } else {
+        for result in results {
+            let primes = result
+                .factors
+                .iter()
+                .flat_map(|&(p, k)| std::iter::repeat(p.to_string()).take(k))
+                .collect::<Vec<_>>();
+            println!("{}: {}", result.n, primes.join(" "));
+        }
+    }
+    Ok(())
+}
+
+fn divisors_command(n: u64, as_json: bool) -> Result<(), String> {
+    let sieve = Sieve::new(10000);
+    let result = match divisors(n as usize, &sieve) {
+        Some(divisors) => Divisors {
+            n: n,
+            divisors: divisors,
+        },
+        None => return Err(format!("{} has infinitely many divisors", n)),
+    };
+    if as_json {
+        println!("{}", json::as_json(&result));
+    } else {
+        let divisors = result
+            .divisors
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+        println!("{}", divisors.join(" "));
+    }
+    Ok(())
+}
+
+fn range_command(from: usize, to: usize, count: bool, as_json: bool) -> Result<(), String> {
+    let sieve = SegmentedSieve::new(to);
+    let primes = sieve.primes_in(from, to);
+    if count {
+        let result = PrimeCount {
+            from: from,
+            to: to,
+            count: primes.count(),
+        };
+        if as_json {
+            println!("{}", json::as_json(&result));
+        } else {
+            println!("{}", result.count);
+        }
+    } else if as_json {
+        let result = PrimeRange {
+            from: from,
+            to: to,
+            primes: primes.collect(),
+        };
+        println!("{}", json::as_json(&result));
+    } else {
+        for prime in primes {
+            println!("{}", prime);
+        }
+    }
+    Ok(())
+}
+
+fn twin_primes_command(from: usize, to: usize, as_json: bool) -> Result<(), String> {
+    let sieve = SegmentedSieve::new(to);
+    let mut previous = None;
+    let mut pairs = Vec::new();
+    for prime in sieve.primes_in(from, to) {
+        if previous == Some(prime - 2) {
+            if as_json {
+                pairs.push((prime - 2, prime));
+            } else {
+                println!("{} {}", prime - 2, prime);
+            }
+        }
+        previous = Some(prime);
+    }
+    if as_json {
+        let result = TwinPrimes {
+            from: from,
+            to: to,
+            pairs: pairs,
+        };
+        println!("{}", json::as_json(&result));
+    }
+    Ok(())
+}
+
+fn run(args: &ArgvMap) -> Result<(), String> {
+    let as_json = args.get_bool("--json");
+    if args.get_bool("is-prime") {
+        is_prime_command(parse_number(args, "<n>")?, as_json)
+    } else if args.get_bool("nth") {
+        nth_command(parse_number(args, "<n>")?, as_json)
+    } else if args.get_bool("factor") {
+        let numbers = args.get_vec("<number>")
+            .into_iter()
+            .map(|value| {
+                value.parse().map_err(
+                    |_| format!("{} is not a valid number", value),
+                )
+            })
+            .collect::<Result<Vec<u64>, String>>()?;
+        factor_command(&numbers, as_json)
+    } else if args.get_bool("divisors") {
+        divisors_command(parse_number(args, "<n>")?, as_json)
+    } else if args.get_bool("range") {
+        let (from, to) = parse_range(args)?;
+        range_command(from, to, args.get_bool("--count"), as_json)
+    } else if args.get_bool("twin-primes") {
+        let (from, to) = parse_range(args)?;
+        twin_primes_command(from, to, as_json)
+    } else {
+        demo();
+        Ok(())
+    }
+}
+
+fn main() {
+    let args = Docopt::new(USAGE)
+        .and_then(|d| d.parse())
+        .unwrap_or_else(|e| e.exit());
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 }
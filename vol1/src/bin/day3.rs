@@ -1,7 +1,14 @@
 extern crate csv;
+extern crate docopt;
+extern crate rand;
 extern crate rustc_serialize;
+extern crate yaml_rust;
 
-use csv::{Reader, Writer};
+use std::fs::File;
+use std::io::{Read, Write};
+
+use csv::Reader;
+use docopt::{ArgvMap, Docopt};
 
 #[derive(RustcDecodable, RustcEncodable)]
 struct Movie {
@@ -10,7 +17,17 @@ struct Movie {
     pub_year: usize,
 }
 
-fn main() {
+mod typed_csv;
+mod convert;
+mod query;
+mod safe_writer;
+
+use typed_csv::{decode_by_header, FromRecord};
+use convert::{convert, Format, Schema};
+use query::Query;
+use safe_writer::{append, AtomicWriter};
+
+fn demo() {
     println!("24 days of Rust - csv (day 3)");
     let dollar_films = vec![
         ("A Fistful of Dollars", "Rojo", 1964),
@@ -18,7 +35,13 @@ fn main() {
         ("The Good, the Bad and the Ugly", "Tuco", 1966),
     ];
     let path = "westerns.csv";
-    let mut writer = Writer::from_file(path).unwrap();
+    let mut writer = match append(path, Movie::columns()) {
+        Ok(writer) => writer,
+        Err(e) => {
+            println!("Can't append to {}: {}", path, e);
+            return;
+        }
+    };
     for row in dollar_films {
         writer.encode(row).expect("CSV writer error");
     }
@@ -29,12 +52,12 @@ fn main() {
     };
     writer.encode(movie).expect("CSV writer error");
     writer.flush().expect("Flush error");
-    let mut reader = Reader::from_file(path).unwrap().has_headers(false);
+    let mut reader = Reader::from_file(path).unwrap();
     for row in reader.decode() {
         let row: (String, String, usize) = row.unwrap();
         println!("{:?}", row);
     }
-    let mut reader = Reader::from_file(path).unwrap().has_headers(false);
+    let mut reader = Reader::from_file(path).unwrap();
     for row in reader.decode() {
         let movie: Movie = row.unwrap();
         println!(
@@ -44,4 +67,130 @@ fn main() {
             movie.pub_year
         );
     }
+    let path = "westerns_with_headers.csv";
+    let mut atomic = AtomicWriter::create(path).unwrap();
+    let rows = vec![
+        ("pub_year", "title", "studio", "bad_guy"),
+        ("1968", "Once Upon a Time in the West", "Paramount", "Frank"),
+        ("sixty-six", "Django", "B.R.C.", "Major Jackson"),
+        ("1971", "Duck, You Sucker!", "Rafran", "Colonel Günther Reza"),
+    ];
+    for row in rows {
+        atomic.writer().encode(row).expect("CSV writer error");
+    }
+    atomic.commit().expect("Commit error");
+    let mut reader = Reader::from_file(path).unwrap();
+    match decode_by_header::<_, Movie>(&mut reader) {
+        Ok(movies) => {
+            for movie in movies {
+                match movie {
+                    Ok(movie) => {
+                        println!(
+                            "{} was a bad guy in '{}' in {}",
+                            movie.bad_guy,
+                            movie.title,
+                            movie.pub_year
+                        )
+                    }
+                    Err(e) => println!("Skipping invalid movie: {}", e),
+                }
+            }
+        }
+        Err(e) => println!("Can't read movies from {}: {}", path, e),
+    }
+    let westerns = r#"[
+        {"title": "Rio Bravo", "bad_guy": "Nathan Burdette", "pub_year": 1959},
+        {"title": "The Magnificent Seven", "bad_guy": "Calvera", "pub_year": 1960,
+         "remake_of": "Seven Samurai"}
+    ]"#;
+    match convert(westerns, Format::Json, Format::Yaml, Schema::Movie) {
+        Ok(conversion) => {
+            print!("{}", conversion.output);
+            for warning in conversion.warnings {
+                println!("warning: {}", warning);
+            }
+        }
+        Err(e) => println!("Conversion failed: {}", e),
+    }
+    let mut reader = Reader::from_file("westerns.csv").unwrap();
+    match Query::new().years(1960, 1969).stats(&mut reader) {
+        Ok(Some(stats)) => {
+            println!(
+                "{} westerns in the sixties, from {} to {}",
+                stats.count,
+                stats.min_year,
+                stats.max_year
+            )
+        }
+        Ok(None) => println!("No westerns in the sixties"),
+        Err(e) => println!("Query failed: {}", e),
+    }
+    let mut reader = Reader::from_file("westerns.csv").unwrap();
+    match Query::new().stats_by_bad_guy(&mut reader) {
+        Ok(groups) => {
+            for (bad_guy, stats) in groups {
+                println!("{} appears in {} movie(s)", bad_guy, stats.count);
+            }
+        }
+        Err(e) => println!("Query failed: {}", e),
+    }
+    let mut reader = Reader::from_file("westerns.csv").unwrap();
+    if let Ok(Some(stats)) = Query::new().stats(&mut reader) {
+        println!("westerns.csv now holds {} movies", stats.count);
+    }
+}
+
+const USAGE: &'static str = "
+Usage:
+    day3
+    day3 convert <input> <output> [--schema=<schema>]
+    day3 (-h | --help)
+
+Formats are picked by file extension: .csv, .json, .yml or .yaml.
+Without a command, runs the demo.
+
+Options:
+    --schema=<schema>  movie or generic [default: movie]
+    -h, --help         display this help and exit
+";
+
+fn convert_command(args: &ArgvMap) -> Result<(), String> {
+    let input_path = args.get_str("<input>");
+    let output_path = args.get_str("<output>");
+    let from = Format::from_path(input_path).ok_or_else(|| {
+        format!("don't know the format of {}", input_path)
+    })?;
+    let to = Format::from_path(output_path).ok_or_else(|| {
+        format!("don't know the format of {}", output_path)
+    })?;
+    let schema = match args.get_str("--schema") {
+        "movie" => Schema::Movie,
+        "generic" => Schema::Generic,
+        other => return Err(format!("unknown schema: {}", other)),
+    };
+    let mut input = String::new();
+    File::open(input_path)
+        .and_then(|mut file| file.read_to_string(&mut input))
+        .map_err(|e| format!("can't read {}: {}", input_path, e))?;
+    let conversion = convert(&input, from, to, schema).map_err(|e| e.to_string())?;
+    for warning in conversion.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    File::create(output_path)
+        .and_then(|mut file| file.write_all(conversion.output.as_bytes()))
+        .map_err(|e| format!("can't write {}: {}", output_path, e))
+}
+
+fn main() {
+    let args = Docopt::new(USAGE)
+        .and_then(|d| d.parse())
+        .unwrap_or_else(|e| e.exit());
+    if !args.get_bool("convert") {
+        demo();
+        return;
+    }
+    if let Err(e) = convert_command(&args) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 }
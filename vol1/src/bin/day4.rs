@@ -1,11 +1,20 @@
-#![feature(plugin)]
-#![plugin(docopt_macros)]
-
-extern crate rustc_serialize;
 extern crate docopt;
+#[cfg(unix)]
+extern crate libc;
+
+use std::fs::File;
+use std::io;
+use std::process;
+
+use docopt::{ArgvMap, Docopt};
+
+mod parallel;
+mod wc;
 
-docopt!(Args, "
-Usage: wc [options] [<file>]
+use wc::Counts;
+
+const USAGE: &'static str = "
+Usage: wc [options] [<file>...]
 
 Options:
     -c, --bytes  print the byte counts
@@ -13,29 +22,188 @@ Options:
     -l, --lines  print the newline counts
     -w, --words  print the word counts
     -L, --max-line-length  print the length of the longest line
+    -j, --jobs=<n>  count on <n> threads at once [default: 1]
     -h, --help  display this help and exit
     -v, --version  output version information and exit
-", arg_file: Option<String>);
+";
 
-fn main() {
-    println!("24 days of Rust - docopt (day 4)");
-    let docopt = Args::docopt();
-    println!("{:?}", docopt);
-    let args: Args = docopt.decode().unwrap_or_else(|e| e.exit());
-    println!("Counting stuff in {}", args.arg_file.unwrap_or("standard input".to_string()));
-    if args.flag_bytes {
-        println!("Counting bytes!");
+#[derive(Debug)]
+struct Args {
+    arg_file: Vec<String>,
+    flag_bytes: bool,
+    flag_chars: bool,
+    flag_lines: bool,
+    flag_words: bool,
+    flag_max_line_length: bool,
+    flag_jobs: usize,
+}
+
+impl Args {
+    fn from_argv(argv: &ArgvMap) -> Result<Args, String> {
+        let jobs = argv.get_str("--jobs");
+        let jobs = match jobs.parse() {
+            Ok(0) | Err(_) => return Err(format!("invalid number of jobs: {}", jobs)),
+            Ok(jobs) => jobs,
+        };
+        Ok(Args {
+            arg_file: argv.get_vec("<file>")
+                .iter()
+                .map(|file| file.to_string())
+                .collect(),
+            flag_bytes: argv.get_bool("--bytes"),
+            flag_chars: argv.get_bool("--chars"),
+            flag_lines: argv.get_bool("--lines"),
+            flag_words: argv.get_bool("--words"),
+            flag_max_line_length: argv.get_bool("--max-line-length"),
+            flag_jobs: jobs,
+        })
+    }
+
+    fn count_selected(&self) -> usize {
+        [
+            self.flag_lines,
+            self.flag_words,
+            self.flag_chars,
+            self.flag_bytes,
+            self.flag_max_line_length,
+        ].iter()
+            .filter(|&&flag| flag)
+            .count()
+    }
+}
+
+/// The size of `file` if it's a regular file, `Some(None)` for pipes,
+/// terminals etc. and `None` if it can't be inspected at all.
+fn regular_file_size(file: &str) -> Option<Option<u64>> {
+    if file == "-" {
+        return Some(stdin_size());
+    }
+    ::std::fs::metadata(file).ok().map(|metadata| if metadata.is_file() {
+        Some(metadata.len())
+    } else {
+        None
+    })
+}
+
+#[cfg(unix)]
+fn stdin_size() -> Option<u64> {
+    let mut stat: libc::stat = unsafe { ::std::mem::zeroed() };
+    if unsafe { libc::fstat(libc::STDIN_FILENO, &mut stat) } == 0 &&
+        stat.st_mode & libc::S_IFMT == libc::S_IFREG
+    {
+        Some(stat.st_size as u64)
+    } else {
+        None
     }
-    if args.flag_chars {
-        println!("Counting characters!");
+}
+
+#[cfg(not(unix))]
+fn stdin_size() -> Option<u64> {
+    None
+}
+
+/// Column width the same way GNU wc picks it: wide enough for the total
+/// size of all regular files, at least 7 if any input isn't a regular file,
+/// and no padding at all for a single count of a single input.
+fn number_width(args: &Args, files: &[String]) -> usize {
+    if files.len() == 1 && args.count_selected() == 1 {
+        return 1;
+    }
+    let mut minimum_width = 1;
+    let mut total = 0;
+    for file in files {
+        match regular_file_size(file) {
+            Some(Some(size)) => total += size,
+            Some(None) => minimum_width = 7,
+            None => {}
+        }
+    }
+    let mut width = 1;
+    while total >= 10 {
+        total /= 10;
+        width += 1;
+    }
+    ::std::cmp::max(width, minimum_width)
+}
+
+fn print_counts(counts: &Counts, args: &Args, width: usize, name: Option<&str>) {
+    let columns = [
+        (args.flag_lines, counts.lines),
+        (args.flag_words, counts.words),
+        (args.flag_chars, counts.chars),
+        (args.flag_bytes, counts.bytes),
+        (args.flag_max_line_length, counts.max_line_length),
+    ];
+    let mut line = columns
+        .iter()
+        .filter(|&&(selected, _)| selected)
+        .map(|&(_, value)| format!("{:>1$}", value, width))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if let Some(name) = name {
+        line.push(' ');
+        line.push_str(name);
+    }
+    println!("{}", line);
+}
+
+fn count_file(file: &str) -> io::Result<Counts> {
+    if file == "-" {
+        let stdin = io::stdin();
+        let handle = stdin.lock();
+        wc::count(handle)
+    } else {
+        wc::count(File::open(file)?)
+    }
+}
+
+fn main() {
+    let argv = Docopt::new(USAGE)
+        .and_then(|d| d.version(Some(env!("CARGO_PKG_VERSION").to_string())).parse())
+        .unwrap_or_else(|e| e.exit());
+    let mut args = Args::from_argv(&argv).unwrap_or_else(|e| {
+        eprintln!("wc: {}", e);
+        process::exit(1);
+    });
+    if args.count_selected() == 0 {
+        args.flag_lines = true;
+        args.flag_words = true;
+        args.flag_bytes = true;
     }
-    if args.flag_lines {
-        println!("Counting lines!");
+    let named = !args.arg_file.is_empty();
+    let files = if named {
+        args.arg_file.clone()
+    } else {
+        vec!["-".to_string()]
+    };
+    let width = number_width(&args, &files);
+    let mut total = Counts::default();
+    let mut failed = false;
+    {
+        let mut report = |file: &str, result: io::Result<Counts>| match result {
+            Ok(counts) => {
+                print_counts(&counts, &args, width, if named { Some(file) } else { None });
+                total += counts;
+            }
+            Err(e) => {
+                eprintln!("wc: {}: {}", file, e);
+                failed = true;
+            }
+        };
+        if args.flag_jobs > 1 {
+            for (file, result) in files.iter().zip(parallel::count_files(&files, args.flag_jobs)) {
+                report(file, result);
+            }
+        } else {
+            for file in &files {
+                report(file, count_file(file));
+            }
+        }
     }
-    if args.flag_words {
-        println!("Counting words!");
+    if files.len() > 1 {
+        print_counts(&total, &args, width, Some("total"));
     }
-    if args.flag_max_line_length {
-        println!("Measuring the longest line!");
+    if failed {
+        process::exit(1);
     }
 }
@@ -1,61 +1,76 @@
 extern crate hyper;
+extern crate rand;
 extern crate rustc_serialize;
 extern crate url;
 
-use std::io::Read;
-use hyper::Client;
-use rustc_serialize::{Encodable, json};
-use url::form_urlencoded;
-
-fn get_content(url: &str) -> hyper::Result<String> {
-    let client = Client::new();
-    let mut response = client.get(url).send()?;
-    let mut buf = String::new();
-    response.read_to_string(&mut buf)?;
-    Ok(buf)
-}
+use std::env;
+use std::time::Duration;
 
-type Query<'a> = Vec<(&'a str, &'a str)>;
-
-fn post_query(url: &str, query: Query) -> hyper::Result<String> {
-    let client = Client::new();
-    let body = form_urlencoded::Serializer::new(String::new())
-        .extend_pairs(query.iter())
-        .finish();
-    let mut response = client.post(url).body(&body[..]).send()?;
-    let mut buf = String::new();
-    response.read_to_string(&mut buf)?;
-    Ok(buf)
-}
+mod client;
+mod multipart;
 
-fn post_json<T>(url: &str, payload: &T) -> hyper::Result<String>
-where
-    T: Encodable,
-{
-    let client = Client::new();
-    let body = json::encode(payload).unwrap();
-    let mut response = client.post(url).body(&body[..]).send()?;
-    let mut buf = String::new();
-    response.read_to_string(&mut buf)?;
-    Ok(buf)
-}
+use client::HttpClient;
+use multipart::Multipart;
 
-#[derive(RustcDecodable, RustcEncodable)]
+#[derive(Debug, RustcDecodable, RustcEncodable)]
 struct Movie {
     title: String,
     bad_guy: String,
     pub_year: usize,
 }
 
+/// The part of httpbin's response that echoes a JSON request body.
+#[derive(RustcDecodable)]
+struct Echo {
+    json: Movie,
+}
+
+#[derive(Debug, RustcDecodable)]
+struct Origin {
+    origin: String,
+}
+
+/// Builds a URL on httpbin.org, or on whatever `HTTPBIN_URL` points to,
+/// such as the `httpbin` stub server from this crate.
+fn httpbin(path: &str) -> String {
+    let base = env::var("HTTPBIN_URL").unwrap_or("http://httpbin.org".to_string());
+    format!("{}{}", base.trim_right_matches('/'), path)
+}
+
 fn main() {
     println!("24 days of Rust - hyper (day 5)");
-    println!("{:?}", get_content("http://httpbin.org/status/200"));
+    let client = HttpClient::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .read_timeout(Duration::from_secs(10))
+        .retries(3, Duration::from_millis(500))
+        .build();
+    println!("{:?}", client.get_content(&httpbin("/status/200")));
     let query = vec![("key", "value"), ("foo", "bar")];
-    println!("{}", post_query("http://httpbin.org/post", query).unwrap());
+    println!("{}", client.post_query(&httpbin("/post"), query).unwrap());
     let movie = Movie {
         title: "You Only Live Twice".to_string(),
         bad_guy: "Blofeld".to_string(),
         pub_year: 1967,
     };
-    println!("{}", post_json("http://httpbin.org/post", &movie).unwrap());
+    let echo: Echo = client.post_json(&httpbin("/post"), &movie).unwrap();
+    println!("{:?}", echo.json);
+    println!("{:?}", client.get_json::<Origin>(&httpbin("/get")));
+    match client.get_json::<Movie>(&httpbin("/status/200")) {
+        Err(e) => println!("{}", e),
+        Ok(_) => println!("an empty response shouldn't decode"),
+    }
+    match client.get_content(&httpbin("/status/404")) {
+        Err(e) => println!("{}", e),
+        Ok(_) => println!("Not Found was expected"),
+    }
+    // westerns.csv is written by the day3 example
+    let notes = "Spaghetti westerns only, please.";
+    let form = Multipart::new()
+        .text("title", "Westerns")
+        .reader("notes", "notes.txt", "text/plain".parse().unwrap(), notes.as_bytes())
+        .file("movies", "westerns.csv");
+    match form {
+        Ok(form) => println!("{}", client.post_multipart(&httpbin("/post"), form).unwrap()),
+        Err(e) => println!("Cannot upload westerns.csv: {}", e),
+    }
 }
@@ -1,11 +1,21 @@
- #![feature(plugin)]
- #![plugin(json_macros)]
-
 extern crate rustc_serialize;
+extern crate url;
 
 use rustc_serialize::Encodable;
 use rustc_serialize::json::{self, Encoder};
 
+#[macro_use]
+mod macros;
+mod patch;
+mod pointer;
+mod stream;
+mod validate;
+
+use patch::{apply_patch, parse_patch};
+use pointer::JsonPointer;
+use stream::{array_items, ndjson_items};
+use validate::Schema;
+
 #[derive(RustcDecodable, RustcEncodable)]
 struct Photo {
     url: String,
@@ -20,6 +30,25 @@ struct User {
     avatar: Option<Photo>,
 }
 
+fn photo_schema() -> Schema {
+    Schema::object()
+        .field("url", Schema::string().url())
+        .field(
+            "dimensions",
+            Schema::tuple(vec![
+                Schema::integer().positive(),
+                Schema::integer().positive(),
+            ]),
+        )
+}
+
+fn user_schema() -> Schema {
+    Schema::object()
+        .field("name", Schema::string().non_empty())
+        .field("post_count", Schema::integer().range(0, 100_000))
+        .field("likes_burgers", Schema::boolean())
+        .field("avatar", photo_schema().nullable())
+}
 
 fn main() {
     println!("24 days of Rust - json (day 6)");
@@ -77,4 +106,62 @@ fn main() {
     });
     println!("Configuration: {}", config);
     println!("Bandwidth: {}", config.search("bandwidth").unwrap());
+    println!(
+        "Bandwidth: {}",
+        config.pointer("/limits/bandwidth").unwrap()
+    );
+    println!(
+        "Allowed methods: {:?}",
+        config.select("/allowed_methods/*").unwrap()
+    );
+    let patch = r#"[
+        {"op": "test", "path": "/hostname", "value": "localhost"},
+        {"op": "replace", "path": "/port", "value": 8080},
+        {"op": "add", "path": "/allowed_methods/-", "value": "put"},
+        {"op": "move", "from": "/limits/rate", "path": "/rate"},
+        {"op": "remove", "path": "/limits/bandwidth"}
+    ]"#;
+    let operations = parse_patch(&json::Json::from_str(patch).unwrap()).unwrap();
+    let mut patched = config.clone();
+    match apply_patch(&mut patched, &operations) {
+        Ok(()) => println!("Patched configuration: {}", patched),
+        Err(e) => println!("Patch failed: {}", e),
+    }
+    let failing = parse_patch(&json::Json::from_str(
+        r#"[{"op": "test", "path": "/port", "value": 80}]"#,
+    ).unwrap()).unwrap();
+    if let Err(e) = apply_patch(&mut patched, &failing) {
+        println!("Patch failed: {}", e);
+    }
+    let schema = user_schema();
+    match schema.decode::<User>(incoming_request) {
+        Ok(user) => println!("{} passed validation", user.name),
+        Err(e) => println!("{}", e),
+    }
+    let invalid_request = "{\"name\":\" \",\"post_count\":-5,\
+                           \"avatar\":{\"url\":\"lorempixel\",\"dimensions\":[160,0]}}";
+    if let Err(e) = schema.decode::<User>(invalid_request) {
+        println!("{}", e);
+    }
+    let users = r#"[
+        {"name": "Alice", "post_count": 12, "likes_burgers": true, "avatar": null},
+        {"name": "Bob", "post_count": "many", "likes_burgers": false, "avatar": null},
+        {"name": "Carol", "post_count": 7, "likes_burgers": false, "avatar": null}
+    ]"#;
+    // A file would be opened with File::open() and wrapped in a BufReader.
+    for user in array_items::<User, _>(users.as_bytes()) {
+        match user {
+            Ok(user) => println!("Streamed {} with {} posts", user.name, user.post_count),
+            Err(e) => println!("Skipped: {}", e),
+        }
+    }
+    let ndjson = "{\"name\":\"Dave\",\"post_count\":3,\"likes_burgers\":true}\n\
+                  \n\
+                  {\"name\":\"Eve\",\"post_count\":5,\"likes_burgers\":false}\n";
+    for user in ndjson_items::<User, _>(ndjson.as_bytes()) {
+        match user {
+            Ok(user) => println!("Streamed {} with {} posts", user.name, user.post_count),
+            Err(e) => println!("Skipped: {}", e),
+        }
+    }
 }
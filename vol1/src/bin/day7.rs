@@ -3,6 +3,10 @@ extern crate itertools;
 
 use itertools::Itertools;
 
+mod adaptors;
+
+use adaptors::IteratorExt;
+
 fn main() {
     println!("24 days of Rust - itertools (day 7)");
     let words = "hello supercalifragilisticexpialidocious programmer".split(|c| c == ' ');
@@ -18,4 +22,21 @@ fn main() {
     for (i, c) in iproduct!(numbers, chars.iter()) {
         println!("{}: {}", i, c);
     }
+    for window in (1..10).windows_step(3, 2) {
+        println!("{:?}", window);
+    }
+    let words = "to be or not to be".split(' ');
+    for (length, words) in words.chunk_by_key(|word| word.len()) {
+        println!("{} letters: {:?}", length, words);
+    }
+    let even = (1..10).map(|x| x * 2);
+    let odd = (1..5).map(|x| x * 2 + 1);
+    let sorted = vec![even.collect::<Vec<_>>(), odd.collect(), vec![1, 20]];
+    println!("{:?}", sorted.into_iter().merge_sorted().collect::<Vec<_>>());
+    for code in "ab".chars().cartesian_power(3) {
+        println!("{}", code.into_iter().collect::<String>());
+    }
+    let runs = "aaabccdddd".chars().run_lengths().collect::<Vec<_>>();
+    println!("{:?}", runs);
+    println!("{}", runs.into_iter().expand_runs().collect::<String>());
 }
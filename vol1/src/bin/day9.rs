@@ -1,7 +1,19 @@
 extern crate anymap;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
 
+use std::env;
 use std::net::Ipv4Addr;
+use std::process;
 use anymap::AnyMap;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+mod config;
+
+use config::{Config, ConfigError, Format, Setting};
 
 #[derive(Debug)]
 enum HostAddress {
@@ -9,12 +21,73 @@ enum HostAddress {
     Ip(Ipv4Addr),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct Port(u32);
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct ConnectionLimit(u32);
 
+impl Serialize for HostAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            HostAddress::DomainName(ref name) => serializer.serialize_str(name),
+            HostAddress::Ip(ref ip) => serializer.collect_str(ip),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HostAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<HostAddress, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        if address.is_empty() {
+            return Err(de::Error::custom("empty host address"));
+        }
+        Ok(match address.parse() {
+            Ok(ip) => HostAddress::Ip(ip),
+            Err(_) => HostAddress::DomainName(address),
+        })
+    }
+}
+
+impl Setting for HostAddress {
+    fn name() -> &'static str {
+        "host_address"
+    }
+}
+
+impl Setting for Port {
+    fn name() -> &'static str {
+        "port"
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.0 > 0 && self.0 <= 65535 {
+            Ok(())
+        } else {
+            Err("expected a number between 1 and 65535".to_string())
+        }
+    }
+}
+
+impl Setting for ConnectionLimit {
+    fn name() -> &'static str {
+        "connection_limit"
+    }
+}
+
+fn load_config() -> Result<Config, ConfigError> {
+    Config::builder()
+        .require::<HostAddress>()
+        .require::<Port>()
+        .register::<ConnectionLimit>()
+        .default(Port(8080))
+        .default(ConnectionLimit(32))
+        .file("data/day9.toml")?
+        .env("DAY9_")?
+        .args(env::args().skip(1))?
+        .build()
+}
+
 fn main() {
     println!("24 days of Rust - anymap (day 9)");
     let mut config = AnyMap::new();
@@ -38,4 +111,58 @@ fn main() {
     if !config.contains::<Option<f64>>() {
         println!("...but not an optional 64-bit float.");
     }
+    let mut settings = match load_config() {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("Configuration error: {}", e);
+            process::exit(1);
+        }
+    };
+    for warning in settings.warnings() {
+        println!("Warning: {}", warning);
+    }
+    println!(
+        "{:?} from {}",
+        settings.get::<HostAddress>().unwrap(),
+        settings.source::<HostAddress>().unwrap()
+    );
+    println!(
+        "{:?} from {}",
+        settings.get::<Port>().unwrap(),
+        settings.source::<Port>().unwrap()
+    );
+    println!(
+        "{:?} from {}",
+        settings.get::<ConnectionLimit>().unwrap(),
+        settings.source::<ConnectionLimit>().unwrap()
+    );
+    match settings.dump(Format::Toml) {
+        Ok(dumped) => print!("{}", dumped),
+        Err(e) => println!("Cannot dump the configuration: {}", e),
+    }
+    match settings.dump(Format::Json) {
+        Ok(dumped) => println!("{}", dumped),
+        Err(e) => println!("Cannot dump the configuration: {}", e),
+    }
+    settings.subscribe(|limit: Option<&ConnectionLimit>| match limit {
+        Some(limit) => println!("Connection limit changed to {}", limit.0),
+        None => println!("Connection limit removed"),
+    });
+    settings.subscribe(|port: Option<&Port>| if let Some(port) = port {
+        println!("Port changed to {}", port.0)
+    });
+    settings.replace(ConnectionLimit(64));
+    {
+        let overridden = settings.scoped(Port(9999));
+        println!(
+            "{:?} from {}",
+            overridden.get::<Port>().unwrap(),
+            overridden.source::<Port>().unwrap()
+        );
+    }
+    println!(
+        "{:?} from {}",
+        settings.get::<Port>().unwrap(),
+        settings.source::<Port>().unwrap()
+    );
 }
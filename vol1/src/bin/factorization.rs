@@ -0,0 +1,260 @@
+use primal::Sieve;
+
+type Factors = Vec<(usize, usize)>;
+
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn add_mod(a: u64, b: u64, m: u64) -> u64 {
+    if a >= m - b { a - (m - b) } else { a + b }
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    if a >> 32 == 0 && b >> 32 == 0 {
+        return a * b % m;
+    }
+    let mut a = a % m;
+    let mut b = b % m;
+    let mut result = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, a, m);
+        }
+        a = add_mod(a, a, m);
+        b >>= 1;
+    }
+    result
+}
+
+fn pow_mod(base: u64, exp: u64, m: u64) -> u64 {
+    let mut base = base % m;
+    let mut exp = exp;
+    let mut result = 1 % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Miller-Rabin test with a witness set that is deterministic for all `u64`.
+fn miller_rabin(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for &p in WITNESSES.iter() {
+        if n % p == 0 {
+            return n == p;
+        }
+    }
+    let mut d = n - 1;
+    let mut s = 0;
+    while d % 2 == 0 {
+        d /= 2;
+        s += 1;
+    }
+    'witness: for &a in WITNESSES.iter() {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Finds a non-trivial divisor of an odd composite `n`.
+fn pollard_rho(n: u64) -> u64 {
+    let mut c = 1;
+    loop {
+        let f = |x| add_mod(mul_mod(x, x, n), c, n);
+        let mut x = 2;
+        let mut y = 2;
+        let mut d = 1;
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(if x > y { x - y } else { y - x }, n);
+        }
+        if d != n {
+            return d;
+        }
+        c += 1;
+    }
+}
+
+fn collect_prime_factors(n: u64, factors: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if miller_rabin(n) {
+        factors.push(n);
+        return;
+    }
+    let d = pollard_rho(n);
+    collect_prime_factors(d, factors);
+    collect_prime_factors(n / d, factors);
+}
+
+pub fn is_prime_u64(n: u64, primes: &Sieve) -> bool {
+    if n <= primes.upper_bound() as u64 {
+        primes.is_prime(n as usize)
+    } else {
+        miller_rabin(n)
+    }
+}
+
+/// `n` as a `usize`, unless it doesn't fit (only possible where `usize`
+/// is narrower than 64 bits).
+pub fn to_usize(n: u64) -> Option<usize> {
+    let m = n as usize;
+    if m as u64 == n { Some(m) } else { None }
+}
+
+/// `n` as a `u64`, unless it doesn't fit.
+pub fn to_u64(n: usize) -> Option<u64> {
+    let m = n as u64;
+    if m as usize == n { Some(m) } else { None }
+}
+
+/// Same as `Sieve::factor`, but whatever is left over after trial division
+/// by the sieve's primes is split with Pollard's rho, so the only errors
+/// are for zero and, on targets with a `usize` narrower than 64 bits, for
+/// prime factors that don't fit in a `usize`. In the latter case the error
+/// holds `usize::MAX` and the smaller factors.
+pub fn factor_u64(n: u64, primes: &Sieve) -> Result<Factors, (usize, Factors)> {
+    let (leftover, mut factors) = match to_usize(n) {
+        Some(n) => {
+            match primes.factor(n) {
+                Ok(factors) => return Ok(factors),
+                Err((0, factors)) => return Err((0, factors)),
+                Err((leftover, factors)) => (leftover as u64, factors),
+            }
+        }
+        None => (n, Vec::new()),
+    };
+    let mut large = Vec::new();
+    collect_prime_factors(leftover, &mut large);
+    large.sort();
+    for p in large {
+        let p = match to_usize(p) {
+            Some(p) => p,
+            None => return Err((usize::MAX, factors)),
+        };
+        match factors.last_mut() {
+            Some(&mut (q, ref mut k)) if q == p => *k += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    Ok(factors)
+}
+
+#[cfg(test)]
+mod tests {
+    use primal::Sieve;
+
+    use super::{factor_u64, miller_rabin, pollard_rho};
+
+    const CARMICHAEL: [u64; 10] = [
+        561,
+        1105,
+        1729,
+        2465,
+        2821,
+        6601,
+        8911,
+        41041,
+        825265,
+        321197185,
+    ];
+
+    // The smallest strong pseudoprimes to all of the first 1, 2, ... prime
+    // bases, each one fooling a longer prefix of the witness list.
+    const STRONG_PSEUDOPRIMES: [u64; 8] = [
+        2047,
+        1373653,
+        25326001,
+        3215031751,
+        2152302898747,
+        3474749660383,
+        341550071728321,
+        3825123056546413051,
+    ];
+
+    #[test]
+    fn agrees_with_sieve() {
+        let sieve = Sieve::new(100_000);
+        for n in 0..100_000 {
+            assert_eq!(miller_rabin(n as u64), sieve.is_prime(n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn carmichael_numbers_are_composite() {
+        for &n in CARMICHAEL.iter() {
+            assert!(!miller_rabin(n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn strong_pseudoprimes_are_composite() {
+        for &n in STRONG_PSEUDOPRIMES.iter() {
+            assert!(!miller_rabin(n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn large_primes() {
+        // 2^61 - 1 is a Mersenne prime.
+        assert!(miller_rabin((1 << 61) - 1));
+        // The largest primes below 2^64 and 2^32.
+        assert!(miller_rabin(18_446_744_073_709_551_557));
+        assert!(miller_rabin(4_294_967_291));
+        assert!(!miller_rabin(u64::max_value()));
+    }
+
+    #[test]
+    fn pollard_rho_splits_semiprimes() {
+        let semiprimes = [
+            (1_000_000_007, 1_000_000_009),
+            (4_294_967_279, 4_294_967_291),
+            (3, 6_148_914_691_236_517_199),
+        ];
+        for &(p, q) in semiprimes.iter() {
+            let n = p * q;
+            assert!(!miller_rabin(n), "{}", n);
+            let d = pollard_rho(n);
+            assert!(d == p || d == q, "{} is not a factor of {}", d, n);
+        }
+    }
+
+    #[test]
+    fn factor_large_numbers() {
+        let sieve = Sieve::new(1000);
+        assert_eq!(
+            factor_u64(4_294_967_279 * 4_294_967_291, &sieve),
+            Ok(vec![(4_294_967_279, 1), (4_294_967_291, 1)])
+        );
+        assert_eq!(
+            factor_u64(1 << 63, &sieve),
+            Ok(vec![(2, 63)])
+        );
+        assert_eq!(
+            factor_u64(2 * 2 * 1_000_000_007 * 1_000_000_007, &sieve),
+            Ok(vec![(2, 2), (1_000_000_007, 2)])
+        );
+        assert_eq!(factor_u64(0, &sieve), Err((0, vec![])));
+    }
+}
@@ -0,0 +1,264 @@
+// A tiny stand-in for http://httpbin.org, so that the HTTP examples can
+// run without network access:
+//
+//     cargo run --bin=httpbin -- 127.0.0.1:8000
+//     HTTPBIN_URL=http://127.0.0.1:8000 cargo run --bin=day5
+
+extern crate hyper;
+extern crate rustc_serialize;
+extern crate url;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::io::Read;
+
+use hyper::header::{Authorization, Basic, ContentType, Host};
+use hyper::method::Method;
+use hyper::mime::{Attr, Mime, SubLevel, TopLevel, Value};
+use hyper::server::{Request, Response, Server};
+use hyper::status::StatusCode;
+use hyper::uri::RequestUri;
+use rustc_serialize::json::{self, Json, ToJson};
+use url::form_urlencoded;
+
+const TEAPOT: &'static str = r#"
+    -=[ teapot ]=-
+
+       _...._
+     .'  _ _ `.
+    | ."` ^ `". _,
+    \_;`"---"`|//
+      |       ;/
+      \_     _/
+        `"""`
+"#;
+
+/// Turns decoded `key=value` pairs into an object the way httpbin does:
+/// a key that appears more than once maps to an array of its values.
+fn pairs_to_json<I>(pairs: I) -> Json
+where
+    I: Iterator<Item = (String, String)>,
+{
+    let mut object = BTreeMap::new();
+    for (key, value) in pairs {
+        let value = Json::String(value);
+        let merged = match object.remove(&key) {
+            None => value,
+            Some(Json::Array(mut values)) => {
+                values.push(value);
+                Json::Array(values)
+            }
+            Some(previous) => Json::Array(vec![previous, value]),
+        };
+        object.insert(key, merged);
+    }
+    Json::Object(object)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Extracts `param="value"` from a `Content-Disposition` header.
+fn disposition_param(headers: &str, param: &str) -> Option<String> {
+    let prefix = format!("; {}=\"", param);
+    headers.find(&prefix[..]).and_then(|start| {
+        let value = &headers[start + prefix.len()..];
+        value.find('"').map(|end| value[..end].to_string())
+    })
+}
+
+/// Splits a `multipart/form-data` body into text fields and files.
+fn parse_multipart(body: &[u8], boundary: &str) -> (Json, Json) {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut form = Vec::new();
+    let mut files = Vec::new();
+    let mut rest = match find(body, &delimiter) {
+        Some(start) => &body[start + delimiter.len()..],
+        None => &[],
+    };
+    // Every part is followed by another delimiter, the last one by "--".
+    while rest.starts_with(b"\r\n") {
+        let end = match find(rest, &delimiter) {
+            Some(end) => end,
+            None => break,
+        };
+        let part = &rest[2..end];
+        let part = if part.ends_with(b"\r\n") {
+            &part[..part.len() - 2]
+        } else {
+            part
+        };
+        rest = &rest[end + delimiter.len()..];
+        let header_end = match find(part, b"\r\n\r\n") {
+            Some(header_end) => header_end,
+            None => continue,
+        };
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+        let content = String::from_utf8_lossy(&part[header_end + 4..]).into_owned();
+        let name = match disposition_param(&headers, "name") {
+            Some(name) => name,
+            None => continue,
+        };
+        if disposition_param(&headers, "filename").is_some() {
+            files.push((name, content));
+        } else {
+            form.push((name, content));
+        }
+    }
+    (pairs_to_json(form.into_iter()), pairs_to_json(files.into_iter()))
+}
+
+fn split_uri(request: &Request) -> (String, String) {
+    let uri = match request.uri {
+        RequestUri::AbsolutePath(ref path) => path.clone(),
+        RequestUri::AbsoluteUri(ref url) => match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        },
+        _ => "/".to_string(),
+    };
+    match uri.find('?') {
+        Some(index) => (uri[..index].to_string(), uri[index + 1..].to_string()),
+        None => (uri, String::new()),
+    }
+}
+
+/// The fields httpbin includes in every echo response.
+fn echo(request: &Request, path: &str, query: &str) -> BTreeMap<String, Json> {
+    let host = request
+        .headers
+        .get::<Host>()
+        .map(|host| match host.port {
+            Some(port) => format!("{}:{}", host.hostname, port),
+            None => host.hostname.clone(),
+        })
+        .unwrap_or_else(|| request.remote_addr.to_string());
+    let mut url = format!("http://{}{}", host, path);
+    if !query.is_empty() {
+        url.push('?');
+        url.push_str(query);
+    }
+    let headers = request
+        .headers
+        .iter()
+        .map(|header| (header.name().to_string(), Json::String(header.value_string())))
+        .collect();
+    let mut object = BTreeMap::new();
+    object.insert(
+        "args".to_string(),
+        pairs_to_json(form_urlencoded::parse(query.as_bytes()).into_owned()),
+    );
+    object.insert("headers".to_string(), Json::Object(headers));
+    object.insert("origin".to_string(), request.remote_addr.ip().to_string().to_json());
+    object.insert("url".to_string(), url.to_json());
+    object
+}
+
+/// Echoes a request with a body: form fields end up in `form`, uploaded
+/// files in `files`, anything else in `data`, parsed into `json` if possible.
+fn echo_with_body(request: &mut Request, path: &str, query: &str) -> BTreeMap<String, Json> {
+    let mut object = echo(request, path, query);
+    let mut body = Vec::new();
+    if let Err(e) = request.read_to_end(&mut body) {
+        body = format!("<unreadable body: {}>", e).into_bytes();
+    }
+    let empty = || Json::Object(BTreeMap::new());
+    let (data, form, files) = match request.headers.get::<ContentType>() {
+        Some(&ContentType(Mime(TopLevel::Application, SubLevel::WwwFormUrlEncoded, _))) => {
+            let form = pairs_to_json(form_urlencoded::parse(&body).into_owned());
+            (String::new(), form, empty())
+        }
+        Some(&ContentType(Mime(TopLevel::Multipart, SubLevel::FormData, ref params))) => {
+            let boundary = params.iter().filter_map(|param| match *param {
+                (Attr::Boundary, Value::Ext(ref boundary)) => Some(boundary.clone()),
+                _ => None,
+            }).next();
+            let (form, files) = parse_multipart(&body, &boundary.unwrap_or_default());
+            (String::new(), form, files)
+        }
+        _ => (String::from_utf8_lossy(&body).into_owned(), empty(), empty()),
+    };
+    object.insert("json".to_string(), Json::from_str(&data).unwrap_or(Json::Null));
+    object.insert("data".to_string(), Json::String(data));
+    object.insert("files".to_string(), files);
+    object.insert("form".to_string(), form);
+    object
+}
+
+fn send_json(mut response: Response, status: StatusCode, object: BTreeMap<String, Json>) {
+    *response.status_mut() = status;
+    response.headers_mut().set(ContentType::json());
+    let body = format!("{}\n", json::as_pretty_json(&Json::Object(object)));
+    if let Err(e) = response.send(body.as_bytes()) {
+        println!("failed to send response: {}", e);
+    }
+}
+
+fn send_status(mut response: Response, status: StatusCode) {
+    *response.status_mut() = status;
+    let body = if status == StatusCode::ImATeapot {
+        TEAPOT
+    } else {
+        ""
+    };
+    if let Err(e) = response.send(body.as_bytes()) {
+        println!("failed to send response: {}", e);
+    }
+}
+
+fn basic_auth(request: &Request, response: Response, user: &str, password: &str) {
+    let authorized = match request.headers.get::<Authorization<Basic>>() {
+        Some(&Authorization(Basic {
+            ref username,
+            password: Some(ref given),
+        })) => username == user && given == password,
+        _ => false,
+    };
+    if authorized {
+        let mut object = BTreeMap::new();
+        object.insert("authenticated".to_string(), Json::Boolean(true));
+        object.insert("user".to_string(), user.to_json());
+        send_json(response, StatusCode::Ok, object);
+    } else {
+        let mut response = response;
+        response
+            .headers_mut()
+            .set_raw("WWW-Authenticate", vec![b"Basic realm=\"Fake Realm\"".to_vec()]);
+        send_status(response, StatusCode::Unauthorized);
+    }
+}
+
+fn handle(mut request: Request, response: Response) {
+    let (path, query) = split_uri(&request);
+    println!("{} {}", request.method, path);
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (request.method.clone(), segments[0], segments.len()) {
+        (_, "status", 2) => match segments[1].parse() {
+            Ok(code) => send_status(response, StatusCode::from_u16(code)),
+            Err(_) => send_status(response, StatusCode::BadRequest),
+        },
+        (Method::Get, "get", 1) => {
+            let object = echo(&request, &path, &query);
+            send_json(response, StatusCode::Ok, object);
+        }
+        (Method::Post, "post", 1) | (Method::Put, "put", 1) => {
+            let object = echo_with_body(&mut request, &path, &query);
+            send_json(response, StatusCode::Ok, object);
+        }
+        (_, "get", 1) | (_, "post", 1) | (_, "put", 1) => {
+            send_status(response, StatusCode::MethodNotAllowed)
+        }
+        (_, "basic-auth", 3) => basic_auth(&request, response, segments[1], segments[2]),
+        _ => send_status(response, StatusCode::NotFound),
+    }
+}
+
+fn main() {
+    let addr = env::args().nth(1).unwrap_or("127.0.0.1:8000".to_string());
+    let server = Server::http(&addr[..]).expect("Failed to bind the stub server");
+    println!("Serving httpbin stub on http://{}", addr);
+    server.handle(handle).expect("Failed to start the stub server");
+}
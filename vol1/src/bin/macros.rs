@@ -0,0 +1,136 @@
+/// Builds a `rustc_serialize::json::Json` value from a JSON-like literal.
+/// Object keys are string literals, `null` is `Json::Null` and any other
+/// value is an expression converted with `ToJson`, so `(500 * 16)` or
+/// `name.clone()` work as well. Trailing commas are allowed, and the
+/// result is the same `Json` that parsing the equivalent text gives.
+///
+/// This is a `macro_rules!` take on the `json_macros` compiler plugin,
+/// which only ever worked on nightly.
+macro_rules! json {
+    // Elements of an array are collected one token tree at a time until
+    // the next comma, since a single element such as `-1` may span more
+    // than one token tree.
+    (@array [$($elems:expr,)*] ()) => {
+        vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] ($($elem:tt)+)) => {
+        vec![$($elems,)* json!($($elem)+)]
+    };
+    (@array [$($elems:expr,)*] ($($elem:tt)+) , $($rest:tt)*) => {
+        json!(@array [$($elems,)* json!($($elem)+),] () $($rest)*)
+    };
+    (@array [$($elems:expr,)*] ($($elem:tt)*) $next:tt $($rest:tt)*) => {
+        json!(@array [$($elems,)*] ($($elem)* $next) $($rest)*)
+    };
+
+    // Same for the `key: value` entries of an object.
+    (@object $object:ident ()) => {};
+    (@object $object:ident ($key:tt : $($value:tt)+)) => {
+        $object.insert(($key).to_string(), json!($($value)+));
+    };
+    (@object $object:ident ($key:tt : $($value:tt)+) , $($rest:tt)*) => {
+        $object.insert(($key).to_string(), json!($($value)+));
+        json!(@object $object () $($rest)*);
+    };
+    (@object $object:ident ($($entry:tt)*) $next:tt $($rest:tt)*) => {
+        json!(@object $object ($($entry)* $next) $($rest)*);
+    };
+
+    (null) => {
+        ::rustc_serialize::json::Json::Null
+    };
+    ([ $($tt:tt)* ]) => {
+        ::rustc_serialize::json::Json::Array(json!(@array [] () $($tt)*))
+    };
+    ({ $($tt:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut object = ::std::collections::BTreeMap::new();
+        json!(@object object () $($tt)*);
+        ::rustc_serialize::json::Json::Object(object)
+    }};
+    ($other:expr) => {{
+        use ::rustc_serialize::json::{Json, ToJson};
+        // The parser reads non-negative integers as `U64`, so `6543` has to
+        // end up the same way for the value to compare equal to parsed JSON.
+        match ($other).to_json() {
+            Json::I64(n) if n >= 0 => Json::U64(n as u64),
+            other => other,
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json::Json;
+
+    fn parse(s: &str) -> Json {
+        Json::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn day6_config() {
+        let config = json!({
+            "hostname": "localhost",
+            "port": 6543,
+            "allowed_methods": ["get", "post"],
+            "limits": {
+                "bandwidth": (500 * 16),
+                "rate": null
+            }
+        });
+        let text = r#"{
+            "hostname": "localhost",
+            "port": 6543,
+            "allowed_methods": ["get", "post"],
+            "limits": {"bandwidth": 8000, "rate": null}
+        }"#;
+        assert_eq!(config, parse(text));
+    }
+
+    #[test]
+    fn nesting() {
+        assert_eq!(json!([]), parse("[]"));
+        assert_eq!(json!({}), parse("{}"));
+        assert_eq!(json!([[], {}, [null]]), parse("[[], {}, [null]]"));
+        assert_eq!(
+            json!({"a": {"b": [{"c": [1, [2]]}]}}),
+            parse(r#"{"a": {"b": [{"c": [1, [2]]}]}}"#)
+        );
+    }
+
+    #[test]
+    fn trailing_commas() {
+        assert_eq!(json!([1, 2,]), parse("[1, 2]"));
+        assert_eq!(json!({"a": 1,}), parse(r#"{"a": 1}"#));
+        assert_eq!(
+            json!({"a": [true,], "b": {"c": null,},}),
+            parse(r#"{"a": [true], "b": {"c": null}}"#)
+        );
+    }
+
+    #[test]
+    fn expression_values() {
+        let name = "Alice".to_string();
+        let key = "name";
+        let likes: Vec<u32> = vec![1, 2];
+        let value = json!({
+            key: name.clone(),
+            "negative": -1,
+            "sum": 2 + 3,
+            "float": 1.5,
+            "likes": likes,
+            "missing": None::<u32>,
+            "flags": [1 > 2, key.len() == 4],
+        });
+        let text = r#"{
+            "name": "Alice",
+            "negative": -1,
+            "sum": 5,
+            "float": 1.5,
+            "likes": [1, 2],
+            "missing": null,
+            "flags": [false, true]
+        }"#;
+        assert_eq!(value, parse(text));
+    }
+}
@@ -0,0 +1,241 @@
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+
+use hyper::header::ContentType;
+use hyper::mime::{Attr, Mime, SubLevel, TopLevel, Value};
+use rand;
+
+/// Guesses the content type of an uploaded file from its extension.
+fn guess_mime(path: &Path) -> Mime {
+    let mime = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => "text/csv",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain",
+        Some("yaml") | Some("yml") => "application/x-yaml",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    };
+    mime.parse().unwrap()
+}
+
+/// Names and filenames go inside quotes in the part headers, so escape
+/// the characters that would break out of them like browsers do.
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// A `multipart/form-data` request body made of text fields and files.
+/// File contents are never loaded into memory; they're read as the body
+/// is being sent.
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<Box<Read + Send>>,
+    len: Option<u64>,
+}
+
+impl Multipart {
+    pub fn new() -> Multipart {
+        Multipart {
+            boundary: format!("------------------------{:016x}", rand::random::<u64>()),
+            parts: Vec::new(),
+            len: Some(0),
+        }
+    }
+
+    fn push<R: Read + Send + 'static>(&mut self, part: R, len: Option<u64>) {
+        self.parts.push(Box::new(part));
+        self.len = match (self.len, len) {
+            (Some(total), Some(len)) => Some(total + len),
+            _ => None,
+        };
+    }
+
+    fn push_bytes(&mut self, bytes: Vec<u8>) {
+        let len = bytes.len() as u64;
+        self.push(Cursor::new(bytes), Some(len));
+    }
+
+    fn push_headers(&mut self, name: &str, file: Option<(&str, &Mime)>) {
+        let mut headers = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            escape(name)
+        );
+        if let Some((filename, mime)) = file {
+            headers.push_str(&format!(
+                "; filename=\"{}\"\r\nContent-Type: {}",
+                escape(filename),
+                mime
+            ));
+        }
+        headers.push_str("\r\n\r\n");
+        self.push_bytes(headers.into_bytes());
+    }
+
+    pub fn text(mut self, name: &str, value: &str) -> Multipart {
+        self.push_headers(name, None);
+        self.push_bytes(format!("{}\r\n", value).into_bytes());
+        self
+    }
+
+    /// Adds the contents of the file at `path`, with a content type
+    /// guessed from its extension.
+    pub fn file<P: AsRef<Path>>(self, name: &str, path: P) -> io::Result<Multipart> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let filename = path.file_name()
+            .and_then(|filename| filename.to_str())
+            .unwrap_or("file")
+            .to_string();
+        Ok(self.part(name, &filename, guess_mime(path), file.take(len), Some(len)))
+    }
+
+    /// Adds a file part read from any source, such as a socket or a pipe.
+    pub fn reader<R>(self, name: &str, filename: &str, mime: Mime, reader: R) -> Multipart
+    where
+        R: Read + Send + 'static,
+    {
+        self.part(name, filename, mime, reader, None)
+    }
+
+    fn part<R>(mut self, name: &str, filename: &str, mime: Mime, reader: R, len: Option<u64>)
+        -> Multipart
+    where
+        R: Read + Send + 'static,
+    {
+        self.push_headers(name, Some((filename, &mime)));
+        self.push(reader, len);
+        self.push_bytes(b"\r\n".to_vec());
+        self
+    }
+
+    pub fn content_type(&self) -> ContentType {
+        ContentType(Mime(
+            TopLevel::Multipart,
+            SubLevel::FormData,
+            vec![(Attr::Boundary, Value::Ext(self.boundary.clone()))],
+        ))
+    }
+
+    /// The request body, along with its length if all the parts have
+    /// a known size.
+    pub fn into_body(mut self) -> (MultipartBody, Option<u64>) {
+        let closing = format!("--{}--\r\n", self.boundary);
+        self.push_bytes(closing.into_bytes());
+        let body = MultipartBody {
+            parts: self.parts.into_iter(),
+            current: None,
+        };
+        (body, self.len)
+    }
+}
+
+impl Default for Multipart {
+    fn default() -> Multipart {
+        Multipart::new()
+    }
+}
+
+pub struct MultipartBody {
+    parts: ::std::vec::IntoIter<Box<Read + Send>>,
+    current: Option<Box<Read + Send>>,
+}
+
+impl Read for MultipartBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.current.is_none() {
+                self.current = self.parts.next();
+            }
+            let n = match self.current {
+                Some(ref mut part) => part.read(buf)?,
+                None => return Ok(0),
+            };
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            self.current = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form() -> Multipart {
+        let mut form = Multipart::new();
+        form.boundary = "BOUNDARY".to_string();
+        form.text("title", "Rio \"Bravo\"")
+    }
+
+    fn render(form: Multipart) -> (String, Option<u64>) {
+        let (mut body, len) = form.into_body();
+        let mut rendered = String::new();
+        body.read_to_string(&mut rendered).unwrap();
+        (rendered, len)
+    }
+
+    #[test]
+    fn content_type_has_the_boundary() {
+        let content_type = form().content_type();
+        assert_eq!(
+            content_type.to_string(),
+            "multipart/form-data; boundary=BOUNDARY"
+        );
+    }
+
+    #[test]
+    fn known_length() {
+        let csv = "title,year\nRio Bravo,1959\n";
+        let form = form().part(
+            "movies",
+            "we\"st\nerns.csv",
+            guess_mime(Path::new("westerns.csv")),
+            Cursor::new(csv),
+            Some(csv.len() as u64),
+        );
+        let (body, len) = render(form);
+        assert_eq!(
+            body,
+            "--BOUNDARY\r\n\
+             Content-Disposition: form-data; name=\"title\"\r\n\
+             \r\n\
+             Rio \"Bravo\"\r\n\
+             --BOUNDARY\r\n\
+             Content-Disposition: form-data; name=\"movies\"; filename=\"we%22st%0Aerns.csv\"\r\n\
+             Content-Type: text/csv\r\n\
+             \r\n\
+             title,year\nRio Bravo,1959\n\r\n\
+             --BOUNDARY--\r\n"
+        );
+        assert_eq!(len, Some(body.len() as u64));
+    }
+
+    #[test]
+    fn unknown_length_is_chunked() {
+        let notes = "Remake of Rio Bravo".as_bytes();
+        let mime = "text/plain".parse().unwrap();
+        let (body, len) = render(form().reader("notes", "notes.txt", mime, notes));
+        assert_eq!(len, None);
+        assert!(body.ends_with(
+            "name=\"notes\"; filename=\"notes.txt\"\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             Remake of Rio Bravo\r\n\
+             --BOUNDARY--\r\n"
+        ));
+    }
+
+    #[test]
+    fn empty_form() {
+        let mut form = Multipart::new();
+        form.boundary = "BOUNDARY".to_string();
+        assert_eq!(render(form), ("--BOUNDARY--\r\n".to_string(), Some(14)));
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use wc::{count_partial, Counts, Partial};
+
+/// Files smaller than this are never split.
+const MIN_CHUNK_SIZE: u64 = 1 << 20;
+
+/// A byte range of one input. `len` is `None` for stdin and other streams
+/// that can only be read from start to end.
+struct Task {
+    input: usize,
+    path: String,
+    offset: u64,
+    len: Option<u64>,
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// Moves `pos` past the continuation bytes of a character that starts
+/// before it, so that chunk boundaries never cut a character in two.
+/// A character takes at most four bytes, so there are at most three to skip.
+fn align(file: &mut File, pos: u64) -> io::Result<u64> {
+    if pos == 0 {
+        return Ok(0);
+    }
+    let mut buf = [0u8; 3];
+    file.seek(SeekFrom::Start(pos))?;
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let skip = buf[..filled]
+        .iter()
+        .take_while(|&&byte| is_continuation(byte))
+        .count();
+    Ok(pos + skip as u64)
+}
+
+fn split(input: usize, path: &str, jobs: usize, tasks: &mut Vec<Task>) -> io::Result<()> {
+    let whole = Task {
+        input: input,
+        path: path.to_string(),
+        offset: 0,
+        len: None,
+    };
+    if path == "-" {
+        tasks.push(whole);
+        return Ok(());
+    }
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    if !metadata.is_file() || metadata.len() < 2 * MIN_CHUNK_SIZE {
+        tasks.push(whole);
+        return Ok(());
+    }
+    let size = metadata.len();
+    let chunk_size = ::std::cmp::max(MIN_CHUNK_SIZE, (size + jobs as u64 - 1) / jobs as u64);
+    let mut start = 0;
+    while start < size {
+        let end = if size - start <= chunk_size {
+            size
+        } else {
+            align(&mut file, start + chunk_size)?
+        };
+        tasks.push(Task {
+            input: input,
+            path: path.to_string(),
+            offset: start,
+            // The last chunk reads to EOF in case the file is still growing.
+            len: if end == size { None } else { Some(end - start) },
+        });
+        start = end;
+    }
+    Ok(())
+}
+
+fn run(task: &Task) -> io::Result<Partial> {
+    if task.path == "-" {
+        let stdin = io::stdin();
+        let handle = stdin.lock();
+        return count_partial(handle);
+    }
+    let mut file = File::open(&task.path)?;
+    file.seek(SeekFrom::Start(task.offset))?;
+    match task.len {
+        Some(len) => count_partial(file.take(len)),
+        None => count_partial(file),
+    }
+}
+
+/// Counts `files` on `jobs` threads, splitting large regular files into
+/// chunks so that a single big file keeps all of them busy too. Results
+/// come back in the order of `files` and are the same as counting each
+/// file on its own.
+pub fn count_files(files: &[String], jobs: usize) -> Vec<io::Result<Counts>> {
+    let mut results: Vec<Option<io::Result<Partial>>> = files.iter().map(|_| None).collect();
+    let mut tasks = Vec::new();
+    for (input, path) in files.iter().enumerate() {
+        if let Err(e) = split(input, path, jobs, &mut tasks) {
+            results[input] = Some(Err(e));
+        }
+    }
+    let task_count = tasks.len();
+    let queue = Arc::new(Mutex::new(tasks.into_iter().enumerate()));
+    let (sender, receiver) = mpsc::channel();
+    let workers: Vec<_> = (0..jobs)
+        .map(|_| {
+            let queue = queue.clone();
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some((index, task)) => {
+                        let result = run(&task);
+                        if sender.send((index, task.input, result)).is_err() {
+                            return;
+                        }
+                    }
+                    None => return,
+                }
+            })
+        })
+        .collect();
+    drop(sender);
+    // Chunks finish in any order, so keep them around until they can be
+    // glued to their predecessors.
+    let mut chunks: Vec<Option<(usize, io::Result<Partial>)>> =
+        (0..task_count).map(|_| None).collect();
+    for (index, input, result) in receiver {
+        chunks[index] = Some((input, result));
+    }
+    for worker in workers {
+        worker.join().expect("counting thread panicked");
+    }
+    for chunk in chunks {
+        let (input, result) = chunk.expect("chunk was never counted");
+        results[input] = Some(match (results[input].take(), result) {
+            (None, result) => result,
+            (Some(Ok(partial)), Ok(next)) => Ok(partial.then(next)),
+            (Some(Err(e)), _) | (Some(Ok(_)), Err(e)) => Err(e),
+        });
+    }
+    results
+        .into_iter()
+        .map(|result| {
+            result
+                .unwrap_or_else(|| Ok(Partial::default()))
+                .map(|partial| partial.counts())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use wc::count;
+
+    use super::*;
+
+    #[test]
+    fn chunks_add_up_to_the_whole_file() {
+        // A unique name, so that concurrent test runs don't share the file.
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let path = env::temp_dir().join(format!(
+            "day4-chunks-{}-{}.txt",
+            now.as_secs(),
+            now.subsec_nanos()
+        ));
+        {
+            let mut file = File::create(&path).unwrap();
+            // Lines of different lengths so that chunk edges land inside
+            // characters, words and whitespace runs.
+            for i in 0..100000 {
+                writeln!(file, "{}wörd 一二\u{A0} 😀\t{}", "x".repeat(i % 7), i).unwrap();
+            }
+        }
+        let files = vec![path.to_str().unwrap().to_string()];
+        let expected = count(File::open(&path).unwrap()).unwrap();
+        for &jobs in &[2, 3, 7] {
+            let mut tasks = Vec::new();
+            split(0, &files[0], jobs, &mut tasks).unwrap();
+            assert!(tasks.len() > 1);
+            let results = count_files(&files, jobs);
+            assert_eq!(results[0].as_ref().unwrap(), &expected, "{} jobs", jobs);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,401 @@
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use rustc_serialize::json::Json;
+
+use pointer::{parse_index, parse_pointer, JsonPointer, PointerError};
+
+#[derive(Debug, PartialEq)]
+pub enum PatchError {
+    Pointer(PointerError),
+    /// The patch document itself is malformed.
+    InvalidOperation(String),
+    /// A path (or `from`) doesn't point at anything in the document.
+    NotFound(String),
+    /// A `test` operation found a different value.
+    TestFailed { path: String, found: Json },
+    /// A value can't be moved into one of its own children.
+    MoveIntoChild { from: String, path: String },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PatchError::Pointer(ref e) => write!(f, "{}", e),
+            PatchError::InvalidOperation(ref reason) => write!(f, "invalid operation: {}", reason),
+            PatchError::NotFound(ref path) => write!(f, "no value at {}", path),
+            PatchError::TestFailed {
+                ref path,
+                ref found,
+            } => write!(f, "test failed, found {} at {}", found, path),
+            PatchError::MoveIntoChild { ref from, ref path } => {
+                write!(f, "cannot move {} into its child {}", from, path)
+            }
+        }
+    }
+}
+
+impl Error for PatchError {
+    fn description(&self) -> &str {
+        match *self {
+            PatchError::Pointer(ref e) => e.description(),
+            PatchError::InvalidOperation(_) => "invalid patch operation",
+            PatchError::NotFound(_) => "path not found",
+            PatchError::TestFailed { .. } => "test operation failed",
+            PatchError::MoveIntoChild { .. } => "cannot move a value into its child",
+        }
+    }
+}
+
+impl From<PointerError> for PatchError {
+    fn from(e: PointerError) -> PatchError {
+        PatchError::Pointer(e)
+    }
+}
+
+/// A single RFC 6902 operation. Paths are JSON Pointers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    Add { path: String, value: Json },
+    Remove { path: String },
+    Replace { path: String, value: Json },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Json },
+}
+
+fn member<'a>(operation: &'a Json, name: &str) -> Result<&'a Json, PatchError> {
+    operation
+        .find(name)
+        .ok_or_else(|| PatchError::InvalidOperation(format!("missing \"{}\" in {}", name, operation)))
+}
+
+fn string_member(operation: &Json, name: &str) -> Result<String, PatchError> {
+    member(operation, name)?
+        .as_string()
+        .map(|value| value.to_string())
+        .ok_or_else(|| PatchError::InvalidOperation(format!("\"{}\" must be a string", name)))
+}
+
+impl Operation {
+    pub fn from_json(operation: &Json) -> Result<Operation, PatchError> {
+        let path = string_member(operation, "path")?;
+        let value = || member(operation, "value").map(|value| value.clone());
+        let from = || string_member(operation, "from");
+        Ok(match &string_member(operation, "op")?[..] {
+            "add" => Operation::Add {
+                path: path,
+                value: value()?,
+            },
+            "remove" => Operation::Remove { path: path },
+            "replace" => Operation::Replace {
+                path: path,
+                value: value()?,
+            },
+            "move" => Operation::Move {
+                from: from()?,
+                path: path,
+            },
+            "copy" => Operation::Copy {
+                from: from()?,
+                path: path,
+            },
+            "test" => Operation::Test {
+                path: path,
+                value: value()?,
+            },
+            op => return Err(PatchError::InvalidOperation(format!("unknown op \"{}\"", op))),
+        })
+    }
+}
+
+/// Reads a patch document, which is an array of operation objects.
+pub fn parse_patch(patch: &Json) -> Result<Vec<Operation>, PatchError> {
+    match *patch {
+        Json::Array(ref operations) => operations.iter().map(Operation::from_json).collect(),
+        _ => Err(PatchError::InvalidOperation(
+            "a patch must be an array".to_string(),
+        )),
+    }
+}
+
+/// Integers compare exactly, even beyond the precision of `f64`. Once a
+/// float is involved numbers compare by value, so that `1`, `1.0` and `-0`
+/// match `test` operations regardless of how they were parsed.
+fn number_eq(a: &Json, b: &Json) -> bool {
+    if a.is_f64() || b.is_f64() {
+        return a.as_f64() == b.as_f64();
+    }
+    match (a.as_u64(), b.as_u64()) {
+        (Some(a), Some(b)) => a == b,
+        (None, None) => a.as_i64() == b.as_i64(),
+        // One of them is negative.
+        _ => false,
+    }
+}
+
+fn json_eq(a: &Json, b: &Json) -> bool {
+    match (a, b) {
+        (&Json::Array(ref a), &Json::Array(ref b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_eq(a, b))
+        }
+        (&Json::Object(ref a), &Json::Object(ref b)) => {
+            a.len() == b.len() &&
+                a.iter().all(|(key, a)| b.get(key).map_or(false, |b| json_eq(a, b)))
+        }
+        (a, b) if a.is_number() && b.is_number() => number_eq(a, b),
+        (a, b) => a == b,
+    }
+}
+
+/// Splits `path` into the pointer to its parent and the last token.
+fn split_last(path: &str) -> Result<Option<(String, String)>, PatchError> {
+    let mut tokens = parse_pointer(path)?;
+    Ok(tokens.pop().map(|last| {
+        let parent = &path[..path.rfind('/').unwrap()];
+        (parent.to_string(), last)
+    }))
+}
+
+fn add(doc: &mut Json, path: &str, value: Json) -> Result<(), PatchError> {
+    let (parent, last) = match split_last(path)? {
+        Some(split) => split,
+        None => {
+            *doc = value;
+            return Ok(());
+        }
+    };
+    match doc.pointer_mut(&parent) {
+        Some(&mut Json::Object(ref mut object)) => {
+            object.insert(last, value);
+            Ok(())
+        }
+        Some(&mut Json::Array(ref mut array)) => {
+            let index = if last == "-" {
+                array.len()
+            } else {
+                match parse_index(&last) {
+                    Some(index) if index <= array.len() => index,
+                    _ => return Err(PatchError::NotFound(path.to_string())),
+                }
+            };
+            array.insert(index, value);
+            Ok(())
+        }
+        _ => Err(PatchError::NotFound(path.to_string())),
+    }
+}
+
+fn remove(doc: &mut Json, path: &str) -> Result<Json, PatchError> {
+    let (parent, last) = match split_last(path)? {
+        Some(split) => split,
+        None => return Ok(mem::replace(doc, Json::Null)),
+    };
+    let removed = match doc.pointer_mut(&parent) {
+        Some(&mut Json::Object(ref mut object)) => object.remove(&last),
+        Some(&mut Json::Array(ref mut array)) => match parse_index(&last) {
+            Some(index) if index < array.len() => Some(array.remove(index)),
+            _ => None,
+        },
+        _ => None,
+    };
+    removed.ok_or_else(|| PatchError::NotFound(path.to_string()))
+}
+
+fn apply_operation(doc: &mut Json, operation: &Operation) -> Result<(), PatchError> {
+    match *operation {
+        Operation::Add {
+            ref path,
+            ref value,
+        } => add(doc, path, value.clone()),
+        Operation::Remove { ref path } => remove(doc, path).map(|_| ()),
+        Operation::Replace {
+            ref path,
+            ref value,
+        } => {
+            parse_pointer(path)?;
+            match doc.pointer_mut(path) {
+                Some(target) => {
+                    *target = value.clone();
+                    Ok(())
+                }
+                None => Err(PatchError::NotFound(path.clone())),
+            }
+        }
+        Operation::Move { ref from, ref path } => {
+            if path.starts_with(&format!("{}/", from)) {
+                return Err(PatchError::MoveIntoChild {
+                    from: from.clone(),
+                    path: path.clone(),
+                });
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        Operation::Copy { ref from, ref path } => {
+            parse_pointer(from)?;
+            let value = doc.pointer(from)
+                .cloned()
+                .ok_or_else(|| PatchError::NotFound(from.clone()))?;
+            add(doc, path, value)
+        }
+        Operation::Test {
+            ref path,
+            ref value,
+        } => {
+            parse_pointer(path)?;
+            match doc.pointer(path) {
+                Some(found) if json_eq(found, value) => Ok(()),
+                Some(found) => Err(PatchError::TestFailed {
+                    path: path.clone(),
+                    found: found.clone(),
+                }),
+                None => Err(PatchError::NotFound(path.clone())),
+            }
+        }
+    }
+}
+
+/// Applies all `operations` in order. Like the RFC requires, a patch is
+/// atomic: if any operation fails, `doc` is left untouched.
+pub fn apply_patch(doc: &mut Json, operations: &[Operation]) -> Result<(), PatchError> {
+    let mut patched = doc.clone();
+    for operation in operations {
+        apply_operation(&mut patched, operation)?;
+    }
+    *doc = patched;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(text: &str) -> Json {
+        Json::from_str(text).unwrap()
+    }
+
+    fn patched(doc: &str, patch: &str) -> Result<Json, PatchError> {
+        let mut doc = json(doc);
+        apply_patch(&mut doc, &parse_patch(&json(patch))?)?;
+        Ok(doc)
+    }
+
+    #[test]
+    fn add() {
+        let doc = r#"{"list": [1, 2]}"#;
+        let patch = r#"[{"op": "add", "path": "/list/1", "value": 9},
+                        {"op": "add", "path": "/list/-", "value": 3},
+                        {"op": "add", "path": "/a~1b", "value": {}}]"#;
+        assert_eq!(
+            patched(doc, patch),
+            Ok(json(r#"{"list": [1, 9, 2, 3], "a/b": {}}"#))
+        );
+        let past_the_end = r#"[{"op": "add", "path": "/list/3", "value": 0}]"#;
+        assert_eq!(
+            patched(doc, past_the_end),
+            Err(PatchError::NotFound("/list/3".to_string()))
+        );
+    }
+
+    #[test]
+    fn remove_and_replace() {
+        let doc = r#"{"list": [1, 2, 3], "name": "x"}"#;
+        let patch = r#"[{"op": "remove", "path": "/list/0"},
+                        {"op": "replace", "path": "/name", "value": [true]}]"#;
+        assert_eq!(
+            patched(doc, patch),
+            Ok(json(r#"{"list": [2, 3], "name": [true]}"#))
+        );
+        for patch in &[
+            r#"[{"op": "remove", "path": "/missing"}]"#,
+            r#"[{"op": "replace", "path": "/missing", "value": 1}]"#,
+        ] {
+            assert_eq!(
+                patched(doc, patch),
+                Err(PatchError::NotFound("/missing".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn move_and_copy() {
+        let doc = r#"{"a": {"b": [1]}, "c": null}"#;
+        let patch = r#"[{"op": "copy", "from": "/a/b", "path": "/c"},
+                        {"op": "move", "from": "/a/b/0", "path": "/d"}]"#;
+        assert_eq!(
+            patched(doc, patch),
+            Ok(json(r#"{"a": {"b": []}, "c": [1], "d": 1}"#))
+        );
+        let into_child = r#"[{"op": "move", "from": "/a", "path": "/a/b/x"}]"#;
+        assert_eq!(
+            patched(doc, into_child),
+            Err(PatchError::MoveIntoChild {
+                from: "/a".to_string(),
+                path: "/a/b/x".to_string(),
+            })
+        );
+        // A sibling that merely shares the prefix is fine.
+        let sibling = r#"[{"op": "move", "from": "/a", "path": "/ab"}]"#;
+        assert_eq!(
+            patched(doc, sibling),
+            Ok(json(r#"{"ab": {"b": [1]}, "c": null}"#))
+        );
+    }
+
+    #[test]
+    fn test_operation() {
+        let doc = r#"{"n": 1, "big": 9007199254740993, "neg": -1}"#;
+        for value in &["1", "1.0", "1e0"] {
+            let patch = format!(r#"[{{"op": "test", "path": "/n", "value": {}}}]"#, value);
+            assert!(patched(doc, &patch).is_ok(), "{}", value);
+        }
+        let patch = r#"[{"op": "test", "path": "/big", "value": 9007199254740992}]"#;
+        assert_eq!(
+            patched(doc, patch),
+            Err(PatchError::TestFailed {
+                path: "/big".to_string(),
+                found: json("9007199254740993"),
+            })
+        );
+        let patch = r#"[{"op": "test", "path": "/neg", "value": -1}]"#;
+        assert!(patched(doc, patch).is_ok());
+        let patch = r#"[{"op": "test", "path": "/neg", "value": 18446744073709551615}]"#;
+        assert!(patched(doc, patch).is_err());
+    }
+
+    #[test]
+    fn failed_patches_roll_back() {
+        let mut doc = json(r#"{"a": 1}"#);
+        let patch = json(
+            r#"[{"op": "add", "path": "/b", "value": 2},
+                {"op": "remove", "path": "/a"},
+                {"op": "test", "path": "/b", "value": 3}]"#,
+        );
+        let result = apply_patch(&mut doc, &parse_patch(&patch).unwrap());
+        assert!(result.is_err());
+        assert_eq!(doc, json(r#"{"a": 1}"#));
+    }
+
+    #[test]
+    fn invalid_operations() {
+        let invalid = |patch| match patched("{}", patch) {
+            Err(PatchError::InvalidOperation(reason)) => reason,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(invalid("{}"), "a patch must be an array");
+        assert_eq!(
+            invalid(r#"[{"op": "rename", "path": "/a"}]"#),
+            "unknown op \"rename\""
+        );
+        assert_eq!(
+            invalid(r#"[{"op": "add", "path": "/a"}]"#),
+            r#"missing "value" in {"op":"add","path":"/a"}"#
+        );
+        assert_eq!(
+            invalid(r#"[{"op": "copy", "path": "/a", "from": 1}]"#),
+            "\"from\" must be a string"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::error::Error;
+use std::fmt;
+
+use rustc_serialize::json::Json;
+
+#[derive(Debug, PartialEq)]
+pub struct PointerError(pub String);
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid JSON pointer: {}", self.0)
+    }
+}
+
+impl Error for PointerError {
+    fn description(&self) -> &str {
+        "invalid JSON pointer"
+    }
+}
+
+/// Splits an RFC 6901 pointer such as `/limits/bandwidth` into unescaped
+/// reference tokens. The empty pointer refers to the whole document.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(PointerError(pointer.to_string()));
+    }
+    pointer[1..]
+        .split('/')
+        .map(|token| {
+            let mut unescaped = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                if c != '~' {
+                    unescaped.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('0') => unescaped.push('~'),
+                    Some('1') => unescaped.push('/'),
+                    _ => return Err(PointerError(pointer.to_string())),
+                }
+            }
+            Ok(unescaped)
+        })
+        .collect()
+}
+
+/// The reverse of the unescaping done by `parse_pointer`.
+pub fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Parses an array index token: no sign and no leading zeros.
+pub fn parse_index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) ||
+        !token.chars().all(|c| c.is_digit(10))
+    {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn step<'a>(json: &'a Json, token: &str) -> Option<&'a Json> {
+    match *json {
+        Json::Object(ref object) => object.get(token),
+        Json::Array(ref array) => parse_index(token).and_then(|index| array.get(index)),
+        _ => None,
+    }
+}
+
+fn step_mut<'a>(json: &'a mut Json, token: &str) -> Option<&'a mut Json> {
+    match *json {
+        Json::Object(ref mut object) => object.get_mut(token),
+        Json::Array(ref mut array) => parse_index(token).and_then(move |index| array.get_mut(index)),
+        _ => None,
+    }
+}
+
+fn walk<'a>(json: &'a Json, tokens: &[String]) -> Option<&'a Json> {
+    match tokens.split_first() {
+        Some((token, rest)) => step(json, token).and_then(|json| walk(json, rest)),
+        None => Some(json),
+    }
+}
+
+fn walk_mut<'a>(json: &'a mut Json, tokens: &[String]) -> Option<&'a mut Json> {
+    match tokens.split_first() {
+        Some((token, rest)) => step_mut(json, token).and_then(|json| walk_mut(json, rest)),
+        None => Some(json),
+    }
+}
+
+fn select_tokens<'a>(json: &'a Json, tokens: &[String], found: &mut Vec<&'a Json>) {
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => {
+            found.push(json);
+            return;
+        }
+    };
+    if token == "*" {
+        match *json {
+            Json::Object(ref object) => for value in object.values() {
+                select_tokens(value, rest, found);
+            },
+            Json::Array(ref array) => for value in array {
+                select_tokens(value, rest, found);
+            },
+            _ => {}
+        }
+    } else if let Some(value) = step(json, token) {
+        select_tokens(value, rest, found);
+    }
+}
+
+/// JSON Pointer lookups on `Json` values, a more precise alternative to
+/// `find_path()` and `search()`.
+pub trait JsonPointer {
+    /// The value `pointer` refers to, if any.
+    fn pointer(&self, pointer: &str) -> Option<&Json>;
+
+    fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Json>;
+
+    /// Like `pointer`, but a `*` token matches every member of an object or
+    /// every element of an array, so `/users/*/name` collects all names.
+    fn select(&self, path: &str) -> Result<Vec<&Json>, PointerError>;
+}
+
+impl JsonPointer for Json {
+    fn pointer(&self, pointer: &str) -> Option<&Json> {
+        parse_pointer(pointer)
+            .ok()
+            .and_then(|tokens| walk(self, &tokens))
+    }
+
+    fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Json> {
+        match parse_pointer(pointer) {
+            Ok(tokens) => walk_mut(self, &tokens),
+            Err(_) => None,
+        }
+    }
+
+    fn select(&self, path: &str) -> Result<Vec<&Json>, PointerError> {
+        let tokens = parse_pointer(path)?;
+        let mut found = Vec::new();
+        select_tokens(self, &tokens, &mut found);
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescaping() {
+        assert_eq!(parse_pointer(""), Ok(vec![]));
+        assert_eq!(parse_pointer("/"), Ok(vec!["".to_string()]));
+        assert_eq!(
+            parse_pointer("/a~1b/c~0d/~01"),
+            Ok(vec!["a/b".to_string(), "c~d".to_string(), "~1".to_string()])
+        );
+        for pointer in &["a/b", "/a~", "/a~2"] {
+            assert_eq!(
+                parse_pointer(pointer),
+                Err(PointerError(pointer.to_string()))
+            );
+        }
+        assert_eq!(escape_token("~1/"), "~01~1");
+    }
+
+    #[test]
+    fn indices() {
+        assert_eq!(parse_index("0"), Some(0));
+        assert_eq!(parse_index("10"), Some(10));
+        let invalid = ["", "01", "00", "-1", "+1", "1.0", "-", "1e3"];
+        for token in &invalid {
+            assert_eq!(parse_index(token), None, "{}", token);
+        }
+        assert_eq!(parse_index("99999999999999999999999"), None);
+    }
+
+    #[test]
+    fn lookups() {
+        let mut doc = Json::from_str(r#"{"a/b": [10, {"": 20}], "m~n": 30}"#).unwrap();
+        assert_eq!(doc.pointer(""), Some(&doc.clone()));
+        assert_eq!(doc.pointer("/a~1b/0"), Some(&Json::U64(10)));
+        assert_eq!(doc.pointer("/a~1b/1/"), Some(&Json::U64(20)));
+        assert_eq!(doc.pointer("/m~0n"), Some(&Json::U64(30)));
+        assert_eq!(doc.pointer("/a~1b/01"), None);
+        assert_eq!(doc.pointer("/a~1b/2"), None);
+        assert_eq!(doc.pointer("/a~1b/-"), None);
+        *doc.pointer_mut("/a~1b/0").unwrap() = Json::Null;
+        assert_eq!(doc.pointer("/a~1b/0"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn wildcards() {
+        let doc = Json::from_str(r#"{"users": [{"name": "a"}, {"name": "b"}, {}]}"#).unwrap();
+        let names = doc.select("/users/*/name").unwrap();
+        let names = names
+            .iter()
+            .filter_map(|name| name.as_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(doc.select("/*/*").unwrap().len(), 3);
+        assert!(doc.select("users").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::io;
+
+use csv::{self, Reader};
+
+use super::Movie;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct YearStats {
+    pub count: usize,
+    pub min_year: usize,
+    pub max_year: usize,
+}
+
+impl YearStats {
+    fn new(year: usize) -> YearStats {
+        YearStats {
+            count: 1,
+            min_year: year,
+            max_year: year,
+        }
+    }
+
+    fn add(&mut self, year: usize) {
+        self.count += 1;
+        self.min_year = min(self.min_year, year);
+        self.max_year = max(self.max_year, year);
+    }
+}
+
+/// Aggregates movies straight from `Reader::decode`, one row at a time,
+/// so only the running totals are ever kept in memory.
+pub struct Query {
+    years: Option<(usize, usize)>,
+}
+
+impl Query {
+    pub fn new() -> Query {
+        Query { years: None }
+    }
+
+    /// Only consider movies released between `from` and `to`, inclusive.
+    pub fn years(mut self, from: usize, to: usize) -> Query {
+        self.years = Some((from, to));
+        self
+    }
+
+    fn for_each_movie<R, F>(&self, reader: &mut Reader<R>, mut f: F) -> csv::Result<()>
+    where
+        R: io::Read,
+        F: FnMut(Movie),
+    {
+        for row in reader.decode() {
+            let movie: Movie = row?;
+            match self.years {
+                Some((from, to)) if movie.pub_year < from || movie.pub_year > to => continue,
+                _ => f(movie),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn stats<R: io::Read>(&self, reader: &mut Reader<R>) -> csv::Result<Option<YearStats>> {
+        let mut stats: Option<YearStats> = None;
+        self.for_each_movie(reader, |movie| {
+            stats = Some(match stats {
+                Some(mut stats) => {
+                    stats.add(movie.pub_year);
+                    stats
+                }
+                None => YearStats::new(movie.pub_year),
+            });
+        })?;
+        Ok(stats)
+    }
+
+    pub fn stats_by_bad_guy<R: io::Read>(
+        &self,
+        reader: &mut Reader<R>,
+    ) -> csv::Result<BTreeMap<String, YearStats>> {
+        let mut groups: BTreeMap<String, YearStats> = BTreeMap::new();
+        self.for_each_movie(reader, |movie| match groups.entry(movie.bad_guy) {
+            Entry::Occupied(mut entry) => entry.get_mut().add(movie.pub_year),
+            Entry::Vacant(entry) => {
+                entry.insert(YearStats::new(movie.pub_year));
+            }
+        })?;
+        Ok(groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csv::Reader;
+
+    use super::*;
+
+    const WESTERNS: &'static str = "A Fistful of Dollars,Rojo,1964\n\
+                                    For a Few Dollars More,El Indio,1965\n\
+                                    The Good the Bad and the Ugly,Tuco,1966\n\
+                                    Hang 'Em High,Wilson,1968\n\
+                                    The Good the Bad and the Ugly,Tuco,1966\n";
+
+    fn westerns() -> Reader<io::Cursor<Vec<u8>>> {
+        Reader::from_string(WESTERNS).has_headers(false)
+    }
+
+    #[test]
+    fn all_years() {
+        let stats = Query::new().stats(&mut westerns()).unwrap();
+        assert_eq!(
+            stats,
+            Some(YearStats {
+                count: 5,
+                min_year: 1964,
+                max_year: 1968,
+            })
+        );
+    }
+
+    #[test]
+    fn year_bounds_are_inclusive() {
+        let query = Query::new().years(1965, 1966);
+        let stats = query.stats(&mut westerns()).unwrap().unwrap();
+        assert_eq!(
+            (stats.count, stats.min_year, stats.max_year),
+            (3, 1965, 1966)
+        );
+        let groups = query.stats_by_bad_guy(&mut westerns()).unwrap();
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec!["El Indio", "Tuco"]);
+        assert_eq!(groups["Tuco"].count, 2);
+        let stats = Query::new()
+            .years(1968, 1968)
+            .stats(&mut westerns())
+            .unwrap();
+        assert_eq!(stats.map(|stats| stats.count), Some(1));
+    }
+
+    #[test]
+    fn nothing_matches() {
+        let query = Query::new().years(1970, 1979);
+        assert_eq!(query.stats(&mut westerns()).unwrap(), None);
+        assert!(query.stats_by_bad_guy(&mut westerns()).unwrap().is_empty());
+        let mut empty = Reader::from_string("").has_headers(false);
+        assert_eq!(Query::new().stats(&mut empty).unwrap(), None);
+    }
+}
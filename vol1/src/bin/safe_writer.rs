@@ -0,0 +1,303 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use csv::{self, Reader, Writer};
+use rand;
+
+#[derive(Debug)]
+pub enum WriteError {
+    Io(io::Error),
+    Csv(csv::Error),
+    HeaderMismatch {
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    PartialRecord,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WriteError::Io(ref e) => write!(f, "I/O error: {}", e),
+            WriteError::Csv(ref e) => write!(f, "CSV error: {}", e),
+            WriteError::HeaderMismatch {
+                ref expected,
+                ref found,
+            } => write!(f, "expected header {:?}, found {:?}", expected, found),
+            WriteError::PartialRecord => {
+                write!(f, "the last record is incomplete, refusing to append")
+            }
+        }
+    }
+}
+
+impl Error for WriteError {
+    fn description(&self) -> &str {
+        match *self {
+            WriteError::Io(ref e) => e.description(),
+            WriteError::Csv(ref e) => e.description(),
+            WriteError::HeaderMismatch { .. } => "header mismatch",
+            WriteError::PartialRecord => "incomplete last record",
+        }
+    }
+}
+
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> WriteError {
+        WriteError::Io(e)
+    }
+}
+
+impl From<csv::Error> for WriteError {
+    fn from(e: csv::Error) -> WriteError {
+        WriteError::Csv(e)
+    }
+}
+
+fn ends_with_newline(file: &mut File, len: u64) -> io::Result<bool> {
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::Start(len - 1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+/// Opens a CSV file for appending. A new or empty file gets `header` as its
+/// first row, an existing one must already start with exactly that header
+/// and end with a complete record.
+pub fn append<P: AsRef<Path>>(path: P, header: &[&str]) -> Result<Writer<File>, WriteError> {
+    let path = path.as_ref();
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    let len = file.metadata()?.len();
+    if len == 0 {
+        let mut writer = Writer::from_writer(file);
+        writer.encode(header)?;
+        writer.flush()?;
+        return Ok(writer);
+    }
+    let found = Reader::from_file(path)?.headers()?;
+    if found != header {
+        return Err(WriteError::HeaderMismatch {
+            expected: header.iter().map(|h| h.to_string()).collect(),
+            found: found,
+        });
+    }
+    if !ends_with_newline(&mut file, len)? {
+        return Err(WriteError::PartialRecord);
+    }
+    Ok(Writer::from_writer(file))
+}
+
+/// Writes CSV to a temporary file next to the target and only moves it in
+/// place on `commit`, so readers see either the old file or the complete
+/// new one. Dropping it without committing throws the temporary file away.
+pub struct AtomicWriter {
+    writer: Writer<File>,
+    file: File,
+    path: PathBuf,
+    temp_path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicWriter {
+    /// The temporary file gets a random name and is created exclusively,
+    /// so a leftover from a crashed run is never reused.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<AtomicWriter> {
+        let path = path.as_ref().to_path_buf();
+        let file_name = path.file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("csv")
+            .to_string();
+        let mut attempts = 0;
+        loop {
+            let temp_name = format!(".{}.{:08x}.tmp", file_name, rand::random::<u32>());
+            let temp_path = path.with_file_name(temp_name);
+            match OpenOptions::new().write(true).create_new(true).open(&temp_path) {
+                Ok(file) => {
+                    return Ok(AtomicWriter {
+                        writer: Writer::from_writer(file.try_clone()?),
+                        file: file,
+                        path: path,
+                        temp_path: temp_path,
+                        committed: false,
+                    })
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 8 => {
+                    attempts += 1
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn writer(&mut self) -> &mut Writer<File> {
+        &mut self.writer
+    }
+
+    pub fn commit(mut self) -> Result<(), WriteError> {
+        self.writer.flush()?;
+        self.file.sync_all()?;
+        fs::rename(&self.temp_path, &self.path)?;
+        self.committed = true;
+        if let Some(dir) = self.path.parent() {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            sync_dir(dir)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AtomicWriter {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::io::Write;
+
+    use super::*;
+
+    /// A fresh directory under the system temp dir, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> TempDir {
+            let path = env::temp_dir().join(format!("day3-{:08x}", rand::random::<u32>()));
+            fs::create_dir(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn entries(&self) -> Vec<String> {
+            let mut names = fs::read_dir(&self.0)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn read(path: &Path) -> String {
+        let mut contents = String::new();
+        File::open(path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    const HEADER: &'static [&'static str] = &["title", "bad_guy", "pub_year"];
+
+    #[test]
+    fn append_writes_the_header_once() {
+        let dir = TempDir::new();
+        let path = dir.0.join("westerns.csv");
+        for year in &["1964", "1965"] {
+            let mut writer = append(&path, HEADER).unwrap();
+            writer.encode(&["Dollars", "Rojo", year][..]).unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(
+            read(&path),
+            "title,bad_guy,pub_year\nDollars,Rojo,1964\nDollars,Rojo,1965\n"
+        );
+    }
+
+    #[test]
+    fn append_checks_the_header() {
+        let dir = TempDir::new();
+        let path = dir.0.join("westerns.csv");
+        File::create(&path)
+            .unwrap()
+            .write_all(b"title,year\nRio Bravo,1959\n")
+            .unwrap();
+        match append(&path, HEADER) {
+            Err(WriteError::HeaderMismatch { expected, found }) => {
+                assert_eq!(expected, HEADER);
+                assert_eq!(found, vec!["title", "year"]);
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("appended under the wrong header"),
+        }
+        assert_eq!(read(&path), "title,year\nRio Bravo,1959\n");
+    }
+
+    #[test]
+    fn append_refuses_partial_records() {
+        let dir = TempDir::new();
+        let path = dir.0.join("westerns.csv");
+        File::create(&path)
+            .unwrap()
+            .write_all(b"title,bad_guy,pub_year\nRio Bravo,Nathan")
+            .unwrap();
+        match append(&path, HEADER) {
+            Err(WriteError::PartialRecord) => {}
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("appended after a partial record"),
+        }
+    }
+
+    #[test]
+    fn atomic_commit() {
+        let dir = TempDir::new();
+        let path = dir.0.join("westerns.csv");
+        File::create(&path).unwrap().write_all(b"old\n").unwrap();
+        let mut first = AtomicWriter::create(&path).unwrap();
+        let second = AtomicWriter::create(&path).unwrap();
+        // Each writer gets its own temporary file, the target is untouched.
+        let entries = dir.entries();
+        assert_eq!(entries.len(), 3);
+        for name in &entries[..2] {
+            assert!(name.starts_with(".westerns.csv."), "{}", name);
+        }
+        assert_eq!(read(&path), "old\n");
+        first.writer().encode(HEADER).unwrap();
+        first.commit().unwrap();
+        assert_eq!(read(&path), "title,bad_guy,pub_year\n");
+        drop(second);
+        assert_eq!(dir.entries(), vec!["westerns.csv"]);
+    }
+
+    #[test]
+    fn atomic_drop_cleans_up() {
+        let dir = TempDir::new();
+        let path = dir.0.join("westerns.csv");
+        {
+            let mut writer = AtomicWriter::create(&path).unwrap();
+            writer.writer().encode(HEADER).unwrap();
+            assert_eq!(dir.entries().len(), 1);
+        }
+        assert!(dir.entries().is_empty());
+    }
+}
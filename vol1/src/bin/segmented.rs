@@ -0,0 +1,235 @@
+use std::cmp::{max, min};
+
+use primal::Sieve;
+
+const SEGMENT_SIZE: usize = 1 << 16;
+
+fn isqrt(n: usize) -> usize {
+    let mut root = (n as f64).sqrt() as usize;
+    // The float estimate can be off by one either way, and squaring it
+    // overflows near `usize::MAX`.
+    while root.checked_mul(root).map_or(true, |square| square > n) {
+        root -= 1;
+    }
+    while (root + 1)
+        .checked_mul(root + 1)
+        .map_or(false, |square| square <= n)
+    {
+        root += 1;
+    }
+    root
+}
+
+/// The first multiple of `p` not below `n`, if there is one.
+fn next_multiple(n: usize, p: usize) -> Option<usize> {
+    match n % p {
+        0 => Some(n),
+        rem => n.checked_add(p - rem),
+    }
+}
+
+/// A sieve that only keeps primes up to `sqrt(limit)` in memory and
+/// crosses off composites one fixed-size segment at a time.
+pub struct SegmentedSieve {
+    limit: usize,
+    base_primes: Vec<usize>,
+}
+
+impl SegmentedSieve {
+    pub fn new(limit: usize) -> SegmentedSieve {
+        let base = Sieve::new(isqrt(limit) + 1);
+        SegmentedSieve {
+            limit: limit,
+            base_primes: base.primes_from(0).collect(),
+        }
+    }
+
+    pub fn upper_bound(&self) -> usize {
+        self.limit
+    }
+
+    pub fn is_prime(&self, n: usize) -> bool {
+        assert!(
+            n <= self.limit,
+            "SegmentedSieve::is_prime: {} is outside the sieve limit {}",
+            n,
+            self.limit
+        );
+        n >= 2 &&
+            self.base_primes
+                .iter()
+                .take_while(|&&p| p <= n / p)
+                .all(|&p| n % p != 0)
+    }
+
+    pub fn primes_from(&self, lo: usize) -> SegmentedPrimes {
+        let first = if lo <= self.limit { Some(lo) } else { None };
+        self.segments(first, self.limit)
+    }
+
+    /// Primes in the half-open window `[lo, hi)`.
+    pub fn primes_in(&self, lo: usize, hi: usize) -> SegmentedPrimes {
+        // `hi` may be one past the limit, which is why the comparison
+        // can't be `hi <= self.limit + 1` at `usize::MAX`.
+        assert!(
+            hi.saturating_sub(1) <= self.limit,
+            "SegmentedSieve::primes_in: {} is outside the sieve limit {}",
+            hi,
+            self.limit
+        );
+        let first = if lo < hi { Some(lo) } else { None };
+        self.segments(first, hi.saturating_sub(1))
+    }
+
+    fn segments(&self, first: Option<usize>, last: usize) -> SegmentedPrimes {
+        SegmentedPrimes {
+            base_primes: &self.base_primes,
+            segment: Vec::with_capacity(SEGMENT_SIZE),
+            segment_start: 0,
+            position: 0,
+            next_start: first,
+            last: last,
+        }
+    }
+}
+
+pub struct SegmentedPrimes<'a> {
+    base_primes: &'a [usize],
+    segment: Vec<bool>,
+    segment_start: usize,
+    position: usize,
+    next_start: Option<usize>,
+    // Inclusive, so that the window can end at `usize::MAX`.
+    last: usize,
+}
+
+impl<'a> SegmentedPrimes<'a> {
+    fn fill(&mut self, start: usize) {
+        let end = min(start.saturating_add(SEGMENT_SIZE - 1), self.last);
+        self.segment.clear();
+        self.segment.resize(end - start + 1, false);
+        for &p in self.base_primes {
+            if p > end / p {
+                break;
+            }
+            let mut multiple = match next_multiple(start, p) {
+                Some(multiple) => max(p * p, multiple),
+                None => continue,
+            };
+            while multiple <= end {
+                self.segment[multiple - start] = true;
+                multiple = match multiple.checked_add(p) {
+                    Some(multiple) => multiple,
+                    None => break,
+                };
+            }
+        }
+        for n in start..min(2, end.saturating_add(1)) {
+            self.segment[n - start] = true;
+        }
+        self.segment_start = start;
+        self.position = 0;
+        self.next_start = if end < self.last { Some(end + 1) } else { None };
+    }
+}
+
+impl<'a> Iterator for SegmentedPrimes<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            while self.position < self.segment.len() {
+                let i = self.position;
+                self.position += 1;
+                if !self.segment[i] {
+                    return Some(self.segment_start + i);
+                }
+            }
+            match self.next_start {
+                Some(start) => self.fill(start),
+                None => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primal::Sieve;
+
+    use super::*;
+
+    const LIMIT: usize = 3 * SEGMENT_SIZE + 17;
+
+    #[test]
+    fn square_roots() {
+        for n in 0..10_000 {
+            let root = isqrt(n);
+            assert!(root * root <= n && n < (root + 1) * (root + 1));
+        }
+        assert_eq!(
+            isqrt(usize::max_value()),
+            (1 << (usize::max_value().count_ones() / 2)) - 1
+        );
+    }
+
+    #[test]
+    fn same_primes_as_primal() {
+        let sieve = SegmentedSieve::new(LIMIT);
+        let expected = Sieve::new(LIMIT).primes_from(0).collect::<Vec<_>>();
+        assert_eq!(sieve.primes_from(0).collect::<Vec<_>>(), expected);
+        for n in 0..LIMIT + 1 {
+            let is_prime = expected.binary_search(&n).is_ok();
+            assert_eq!(sieve.is_prime(n), is_prime, "{}", n);
+        }
+    }
+
+    #[test]
+    fn windows_across_segments() {
+        let sieve = SegmentedSieve::new(LIMIT);
+        let primal = Sieve::new(LIMIT);
+        let windows = [
+            (0, 2),
+            (0, 3),
+            (SEGMENT_SIZE - 100, SEGMENT_SIZE + 100),
+            (SEGMENT_SIZE, 2 * SEGMENT_SIZE),
+            (1, 3 * SEGMENT_SIZE + 1),
+            (2 * SEGMENT_SIZE + 1, LIMIT + 1),
+        ];
+        for &(lo, hi) in &windows {
+            let expected = primal
+                .primes_from(0)
+                .skip_while(|&p| p < lo)
+                .take_while(|&p| p < hi)
+                .collect::<Vec<_>>();
+            assert_eq!(sieve.primes_in(lo, hi).collect::<Vec<_>>(), expected);
+        }
+        assert_eq!(sieve.primes_in(100, 100).count(), 0);
+        assert_eq!(sieve.primes_in(100, 0).count(), 0);
+        assert_eq!(sieve.primes_from(LIMIT + 1).count(), 0);
+    }
+
+    #[test]
+    fn top_of_the_range() {
+        // A full sieve up to `usize::MAX` needs all primes below 2^32,
+        // but a few small ones are enough to check the arithmetic.
+        let base_primes = vec![2, 3, 5, 7];
+        let sieve = SegmentedSieve {
+            limit: usize::max_value(),
+            base_primes: base_primes.clone(),
+        };
+        let lo = usize::max_value() - 1000;
+        let expected = (lo..usize::max_value())
+            .chain(Some(usize::max_value()))
+            .filter(|&n| base_primes.iter().all(|&p| n % p != 0))
+            .collect::<Vec<_>>();
+        assert_eq!(sieve.primes_from(lo).collect::<Vec<_>>(), expected);
+        // 2^64 - 1 is divisible by 3, so take the last candidate off.
+        let last = usize::max_value() - 2;
+        assert_eq!(expected.last(), Some(&last));
+        assert_eq!(
+            sieve.primes_in(lo, last).collect::<Vec<_>>(),
+            &expected[..expected.len() - 1]
+        );
+    }
+}
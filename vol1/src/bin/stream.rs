@@ -0,0 +1,393 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::str;
+
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{self, DecoderError, ErrorCode, Json, JsonEvent, Parser, ParserError,
+                            StackElement};
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Syntax(ParserError),
+    /// The document doesn't start with `[`.
+    NotAnArray,
+    /// The array element at `index` doesn't decode into the requested type.
+    Item { index: usize, error: DecoderError },
+    /// The given (1-based) line of NDJSON input is invalid.
+    Line { line: usize, error: DecoderError },
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StreamError::Io(ref e) => write!(f, "{}", e),
+            StreamError::Syntax(ref e) => write!(f, "{}", e),
+            StreamError::NotAnArray => write!(f, "expected a JSON array"),
+            StreamError::Item { index, ref error } => write!(f, "item {}: {}", index, error),
+            StreamError::Line { line, ref error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl Error for StreamError {
+    fn description(&self) -> &str {
+        match *self {
+            StreamError::Io(ref e) => e.description(),
+            StreamError::Syntax(_) => "invalid JSON",
+            StreamError::NotAnArray => "expected a JSON array",
+            StreamError::Item { .. } | StreamError::Line { .. } => "cannot decode record",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            StreamError::Io(ref e) => Some(e),
+            StreamError::Syntax(ref e) => Some(e),
+            StreamError::NotAnArray => None,
+            StreamError::Item { ref error, .. } | StreamError::Line { ref error, .. } => Some(error),
+        }
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+}
+
+/// Decodes UTF-8 from a reader one character at a time, which is what
+/// `json::Parser` consumes. An I/O error ends the iteration and is kept
+/// in `error` for the caller to pick up, since the parser only sees
+/// an unexpected end of input.
+struct Chars<R> {
+    bytes: io::Bytes<R>,
+    error: Rc<RefCell<Option<io::Error>>>,
+}
+
+impl<R: Read> Chars<R> {
+    fn decode(&mut self) -> io::Result<Option<char>> {
+        let first = match self.bytes.next() {
+            Some(byte) => byte?,
+            None => return Ok(None),
+        };
+        let width = match first {
+            0x00...0x7F => return Ok(Some(first as char)),
+            0xC2...0xDF => 2,
+            0xE0...0xEF => 3,
+            0xF0...0xF4 => 4,
+            _ => return Err(invalid_utf8()),
+        };
+        let mut buf = [first, 0, 0, 0];
+        for byte in &mut buf[1..width] {
+            *byte = match self.bytes.next() {
+                Some(byte) => byte?,
+                None => return Err(invalid_utf8()),
+            };
+        }
+        match str::from_utf8(&buf[..width]) {
+            Ok(s) => Ok(s.chars().next()),
+            Err(_) => Err(invalid_utf8()),
+        }
+    }
+}
+
+impl<R: Read> Iterator for Chars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        match self.decode() {
+            Ok(c) => c,
+            Err(e) => {
+                *self.error.borrow_mut() = Some(e);
+                None
+            }
+        }
+    }
+}
+
+/// Iterates over the elements of a top-level JSON array, decoding each
+/// into a `T` as soon as it has been read. Only one element is held in
+/// memory at a time, no matter how long the array is.
+pub struct ArrayItems<T, R> {
+    parser: Parser<Chars<R>>,
+    error: Rc<RefCell<Option<io::Error>>>,
+    index: usize,
+    started: bool,
+    finished: bool,
+    marker: PhantomData<T>,
+}
+
+/// Wrap `reader` in a `BufReader` first, the parser reads byte by byte.
+pub fn array_items<T: Decodable, R: Read>(reader: R) -> ArrayItems<T, R> {
+    let error = Rc::new(RefCell::new(None));
+    let chars = Chars {
+        bytes: reader.bytes(),
+        error: error.clone(),
+    };
+    ArrayItems {
+        parser: Parser::new(chars),
+        error: error,
+        index: 0,
+        started: false,
+        finished: false,
+        marker: PhantomData,
+    }
+}
+
+impl<T: Decodable, R: Read> ArrayItems<T, R> {
+    fn event(&mut self) -> Result<Option<JsonEvent>, StreamError> {
+        let event = self.parser.next();
+        if let Some(e) = self.error.borrow_mut().take() {
+            return Err(StreamError::Io(e));
+        }
+        match event {
+            Some(JsonEvent::Error(e)) => Err(StreamError::Syntax(e)),
+            event => Ok(event),
+        }
+    }
+
+    /// Assembles a single value from parser events, much like
+    /// `json::Builder` does for a whole document.
+    fn build(&mut self, event: JsonEvent) -> Result<Json, StreamError> {
+        let eof = || {
+            StreamError::Syntax(ParserError::SyntaxError(ErrorCode::EOFWhileParsingValue, 0, 0))
+        };
+        Ok(match event {
+            JsonEvent::NullValue => Json::Null,
+            JsonEvent::BooleanValue(b) => Json::Boolean(b),
+            JsonEvent::I64Value(n) => Json::I64(n),
+            JsonEvent::U64Value(n) => Json::U64(n),
+            JsonEvent::F64Value(n) => Json::F64(n),
+            JsonEvent::StringValue(s) => Json::String(s),
+            JsonEvent::ArrayStart => {
+                let mut array = Vec::new();
+                loop {
+                    match self.event()? {
+                        Some(JsonEvent::ArrayEnd) => break,
+                        Some(event) => array.push(self.build(event)?),
+                        None => return Err(eof()),
+                    }
+                }
+                Json::Array(array)
+            }
+            JsonEvent::ObjectStart => {
+                let mut object = BTreeMap::new();
+                loop {
+                    let event = match self.event()? {
+                        Some(JsonEvent::ObjectEnd) => break,
+                        Some(event) => event,
+                        None => return Err(eof()),
+                    };
+                    let key = match self.parser.stack().top() {
+                        Some(StackElement::Key(key)) => key.to_string(),
+                        _ => unreachable!(),
+                    };
+                    let value = self.build(event)?;
+                    object.insert(key, value);
+                }
+                Json::Object(object)
+            }
+            JsonEvent::ObjectEnd | JsonEvent::ArrayEnd | JsonEvent::Error(_) => unreachable!(),
+        })
+    }
+
+    fn next_value(&mut self) -> Result<Option<Json>, StreamError> {
+        if !self.started {
+            self.started = true;
+            match self.event()? {
+                Some(JsonEvent::ArrayStart) => {}
+                _ => return Err(StreamError::NotAnArray),
+            }
+        }
+        match self.event()? {
+            // Reports trailing characters after the array, if any.
+            Some(JsonEvent::ArrayEnd) => self.event().map(|_| None),
+            Some(event) => self.build(event).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T: Decodable, R: Read> Iterator for ArrayItems<T, R> {
+    type Item = Result<T, StreamError>;
+
+    /// A value that doesn't decode into `T` is reported and skipped,
+    /// any other error ends the iteration.
+    fn next(&mut self) -> Option<Result<T, StreamError>> {
+        if self.finished {
+            return None;
+        }
+        let json = match self.next_value() {
+            Ok(Some(json)) => json,
+            Ok(None) => {
+                self.finished = true;
+                return None;
+            }
+            Err(e) => {
+                self.finished = true;
+                return Some(Err(e));
+            }
+        };
+        let index = self.index;
+        self.index += 1;
+        let mut decoder = json::Decoder::new(json);
+        Some(T::decode(&mut decoder).map_err(|error| {
+            StreamError::Item {
+                index: index,
+                error: error,
+            }
+        }))
+    }
+}
+
+/// Iterates over newline-delimited JSON (one value per line), skipping
+/// blank lines.
+pub struct NdjsonItems<T, R> {
+    lines: io::Lines<R>,
+    line: usize,
+    finished: bool,
+    marker: PhantomData<T>,
+}
+
+pub fn ndjson_items<T: Decodable, R: BufRead>(reader: R) -> NdjsonItems<T, R> {
+    NdjsonItems {
+        lines: reader.lines(),
+        line: 0,
+        finished: false,
+        marker: PhantomData,
+    }
+}
+
+impl<T: Decodable, R: BufRead> Iterator for NdjsonItems<T, R> {
+    type Item = Result<T, StreamError>;
+
+    fn next(&mut self) -> Option<Result<T, StreamError>> {
+        while !self.finished {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Some(Err(StreamError::Io(e)));
+                }
+                None => {
+                    self.finished = true;
+                    return None;
+                }
+            };
+            self.line += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line_number = self.line;
+            return Some(json::decode(&line).map_err(|error| {
+                StreamError::Line {
+                    line: line_number,
+                    error: error,
+                }
+            }));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, RustcDecodable)]
+    struct Post {
+        title: String,
+        likes: u32,
+    }
+
+    fn post(title: &str, likes: u32) -> Post {
+        Post {
+            title: title.to_string(),
+            likes: likes,
+        }
+    }
+
+    fn posts(json: &str) -> Vec<Result<Post, StreamError>> {
+        array_items(json.as_bytes()).collect()
+    }
+
+    #[test]
+    fn malformed_items_are_skipped() {
+        let json = r#"[{"title": "Hello", "likes": 12},
+                       {"title": "Bob", "likes": "many"},
+                       {"title": "World", "likes": 7}]"#;
+        let mut items = posts(json).into_iter();
+        assert_eq!(items.next().unwrap().unwrap(), post("Hello", 12));
+        match items.next().unwrap() {
+            Err(StreamError::Item { index: 1, .. }) => {}
+            other => panic!("unexpected item: {:?}", other),
+        }
+        assert_eq!(items.next().unwrap().unwrap(), post("World", 7));
+        assert!(items.next().is_none());
+    }
+
+    #[test]
+    fn empty_arrays() {
+        assert!(posts("[]").is_empty());
+        assert!(posts(" \n[ ] \n").is_empty());
+        match posts("").as_slice() {
+            [Err(StreamError::Syntax(_))] => {}
+            other => panic!("unexpected items: {:?}", other),
+        }
+        for json in &["{}", "1"] {
+            match posts(json).as_slice() {
+                [Err(StreamError::NotAnArray)] => {}
+                other => panic!("unexpected items for {:?}: {:?}", json, other),
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_input() {
+        for json in &[
+            r#"[{"title": "Hello", "likes": 12}, {"title": "#,
+            r#"[{"title": "Hello", "likes": 12},"#,
+            r#"[{"title": "Hello", "likes": 12}"#,
+            r#"[{"title": "Hello", "likes": 12}] ]"#,
+        ] {
+            let mut items = posts(json).into_iter();
+            assert_eq!(items.next().unwrap().unwrap(), post("Hello", 12));
+            match items.next() {
+                Some(Err(StreamError::Syntax(_))) => {}
+                other => panic!("unexpected item for {:?}: {:?}", json, other),
+            }
+            assert!(items.next().is_none());
+        }
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut items = array_items::<Post, _>(&b"[{\"title\": \"\xff\", \"likes\": 1}]"[..]);
+        match items.next() {
+            Some(Err(StreamError::Io(ref e))) if e.kind() == io::ErrorKind::InvalidData => {}
+            other => panic!("unexpected item: {:?}", other),
+        }
+        assert!(items.next().is_none());
+    }
+
+    #[test]
+    fn ndjson_blank_lines() {
+        let ndjson = "\n{\"title\": \"Hello\", \"likes\": 12}\n  \n\n\
+                      {\"title\": \"World\", \"likes\": 7}\r\n\
+                      {\"title\": \"Bob\", \"likes\": \"many\"}\n\
+                      \n";
+        let items = ndjson_items::<Post, _>(ndjson.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap(), &post("Hello", 12));
+        assert_eq!(items[1].as_ref().unwrap(), &post("World", 7));
+        match items[2] {
+            Err(StreamError::Line { line: 6, .. }) => {}
+            ref other => panic!("unexpected item: {:?}", other),
+        }
+        assert_eq!(ndjson_items::<Post, _>(&b"\n \n"[..]).count(), 0);
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use csv::{self, Reader, StringRecords};
+
+use super::Movie;
+
+#[derive(Debug)]
+pub enum ReadError {
+    Csv(csv::Error),
+    MissingColumn(String),
+    InvalidField {
+        row: usize,
+        column: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadError::Csv(ref e) => write!(f, "CSV error: {}", e),
+            ReadError::MissingColumn(ref column) => write!(f, "missing column '{}'", column),
+            ReadError::InvalidField {
+                row,
+                ref column,
+                ref value,
+                ref reason,
+            } => {
+                write!(
+                    f,
+                    "row {}, column '{}': can't parse {:?} ({})",
+                    row,
+                    column,
+                    value,
+                    reason
+                )
+            }
+        }
+    }
+}
+
+impl Error for ReadError {
+    fn description(&self) -> &str {
+        match *self {
+            ReadError::Csv(ref e) => e.description(),
+            ReadError::MissingColumn(_) => "missing column",
+            ReadError::InvalidField { .. } => "invalid field",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            ReadError::Csv(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<csv::Error> for ReadError {
+    fn from(e: csv::Error) -> ReadError {
+        ReadError::Csv(e)
+    }
+}
+
+/// A single data row together with the positions of the named columns.
+pub struct Record<'a> {
+    row: usize,
+    columns: &'a HashMap<&'static str, usize>,
+    fields: &'a [String],
+}
+
+impl<'a> Record<'a> {
+    pub fn get<T>(&self, column: &'static str) -> Result<T, ReadError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = self.columns
+            .get(column)
+            .and_then(|&index| self.fields.get(index))
+            .map(|value| value.as_str())
+            .unwrap_or("");
+        value.parse().map_err(|e: T::Err| {
+            ReadError::InvalidField {
+                row: self.row,
+                column: column.to_string(),
+                value: value.to_string(),
+                reason: e.to_string(),
+            }
+        })
+    }
+}
+
+/// Types that can be built from a CSV row by looking up columns by name.
+pub trait FromRecord: Sized {
+    fn columns() -> &'static [&'static str];
+    fn from_record(record: &Record) -> Result<Self, ReadError>;
+}
+
+pub struct TypedRecords<'a, R: 'a, T> {
+    records: StringRecords<'a, R>,
+    columns: HashMap<&'static str, usize>,
+    row: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, R, T> Iterator for TypedRecords<'a, R, T>
+where
+    R: io::Read,
+    T: FromRecord,
+{
+    type Item = Result<T, ReadError>;
+
+    fn next(&mut self) -> Option<Result<T, ReadError>> {
+        self.records.next().map(|fields| {
+            self.row += 1;
+            let fields = fields?;
+            let record = Record {
+                row: self.row,
+                columns: &self.columns,
+                fields: &fields,
+            };
+            T::from_record(&record)
+        })
+    }
+}
+
+/// Like `Reader::decode`, but matches struct fields to columns by their
+/// header names instead of by position. Column order doesn't matter and
+/// columns the type doesn't ask for are skipped.
+pub fn decode_by_header<R, T>(reader: &mut Reader<R>) -> Result<TypedRecords<R, T>, ReadError>
+where
+    R: io::Read,
+    T: FromRecord,
+{
+    let headers = reader.headers()?;
+    let mut columns = HashMap::new();
+    for &column in T::columns() {
+        match headers.iter().position(|header| header.trim() == column) {
+            Some(index) => {
+                columns.insert(column, index);
+            }
+            None => return Err(ReadError::MissingColumn(column.to_string())),
+        }
+    }
+    Ok(TypedRecords {
+        records: reader.records(),
+        columns: columns,
+        row: 0,
+        _marker: PhantomData,
+    })
+}
+
+impl FromRecord for Movie {
+    fn columns() -> &'static [&'static str] {
+        &["title", "bad_guy", "pub_year"]
+    }
+
+    fn from_record(record: &Record) -> Result<Movie, ReadError> {
+        Ok(Movie {
+            title: record.get("title")?,
+            bad_guy: record.get("bad_guy")?,
+            pub_year: record.get("pub_year")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csv::Reader;
+
+    use super::*;
+
+    fn movies(data: &str) -> Result<Vec<Result<Movie, ReadError>>, ReadError> {
+        let mut reader = Reader::from_string(data);
+        let records = decode_by_header::<_, Movie>(&mut reader)?;
+        Ok(records.collect())
+    }
+
+    #[test]
+    fn reordered_columns() {
+        let data = "pub_year, rating ,title,bad_guy\n1964,8.0,A Fistful of Dollars,Rojo\n";
+        let movie = movies(data).unwrap().remove(0).unwrap();
+        assert_eq!(movie.title, "A Fistful of Dollars");
+        assert_eq!(movie.bad_guy, "Rojo");
+        assert_eq!(movie.pub_year, 1964);
+    }
+
+    #[test]
+    fn missing_column() {
+        match movies("title,year\nRio Bravo,1959\n") {
+            Err(ReadError::MissingColumn(ref column)) => assert_eq!(column, "bad_guy"),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("decoded without a bad_guy column"),
+        }
+    }
+
+    #[test]
+    fn rows_are_numbered_in_errors() {
+        let data = "title,bad_guy,pub_year\n\
+                    Rio Bravo,Nathan Burdette,1959\n\
+                    El Dorado,Bart Jason,soon\n\
+                    Hombre,Grimes,\n\
+                    The Shootist,Mike Sweeney,1976\n";
+        let mut rows = movies(data).unwrap().into_iter();
+        assert_eq!(rows.next().unwrap().unwrap().pub_year, 1959);
+        match rows.next().unwrap() {
+            Err(e @ ReadError::InvalidField { .. }) => assert_eq!(
+                e.to_string(),
+                "row 2, column 'pub_year': can't parse \"soon\" (invalid digit found in string)"
+            ),
+            _ => panic!("row 2 should be invalid"),
+        }
+        match rows.next().unwrap() {
+            Err(ReadError::InvalidField { row, ref value, .. }) => {
+                assert_eq!(row, 3);
+                assert_eq!(value, "");
+            }
+            _ => panic!("row 3 should be invalid"),
+        }
+        // Errors don't stop the iteration.
+        assert_eq!(rows.next().unwrap().unwrap().title, "The Shootist");
+        assert!(rows.next().is_none());
+    }
+}
@@ -0,0 +1,471 @@
+use std::error::Error;
+use std::fmt;
+
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{self, DecoderError, Json, ParserError};
+use url::Url;
+
+use pointer::escape_token;
+
+/// A rule broken by some part of a JSON document. The path is a JSON
+/// Pointer, so `/avatar/dimensions/0` is the width of the avatar.
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "document: {}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    Syntax(ParserError),
+    Invalid(Vec<Violation>),
+    Decode(DecoderError),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InputError::Syntax(ref e) => write!(f, "invalid JSON: {}", e),
+            InputError::Invalid(ref violations) => {
+                write!(f, "{} validation error(s)", violations.len())?;
+                for violation in violations {
+                    write!(f, "\n  {}", violation)?;
+                }
+                Ok(())
+            }
+            InputError::Decode(ref e) => write!(f, "cannot decode: {}", e),
+        }
+    }
+}
+
+impl Error for InputError {
+    fn description(&self) -> &str {
+        match *self {
+            InputError::Syntax(_) => "invalid JSON",
+            InputError::Invalid(_) => "validation failed",
+            InputError::Decode(_) => "cannot decode JSON",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            InputError::Syntax(ref e) => Some(e),
+            InputError::Invalid(_) => None,
+            InputError::Decode(ref e) => Some(e),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Check {
+    NonEmpty,
+    Range(i64, i64),
+    Url,
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Boolean,
+    Integer,
+    String,
+    Tuple(Vec<Schema>),
+    Object(Vec<(String, Schema)>),
+}
+
+/// The expected shape of a JSON document along with additional rules
+/// for its values, built up much like the types it's going to be
+/// decoded into:
+///
+/// ```ignore
+/// Schema::object()
+///     .field("name", Schema::string().non_empty())
+///     .field("post_count", Schema::integer().range(0, 10000))
+/// ```
+#[derive(Clone, Debug)]
+pub struct Schema {
+    kind: Kind,
+    nullable: bool,
+    checks: Vec<Check>,
+}
+
+fn type_name(json: &Json) -> &'static str {
+    match *json {
+        Json::I64(_) | Json::U64(_) => "an integer",
+        Json::F64(_) => "a number",
+        Json::String(_) => "a string",
+        Json::Boolean(_) => "a boolean",
+        Json::Array(_) => "an array",
+        Json::Object(_) => "an object",
+        Json::Null => "null",
+    }
+}
+
+fn violate(violations: &mut Vec<Violation>, path: &str, message: String) {
+    violations.push(Violation {
+        path: path.to_string(),
+        message: message,
+    });
+}
+
+impl Schema {
+    fn new(kind: Kind) -> Schema {
+        Schema {
+            kind: kind,
+            nullable: false,
+            checks: Vec::new(),
+        }
+    }
+
+    pub fn boolean() -> Schema {
+        Schema::new(Kind::Boolean)
+    }
+
+    pub fn integer() -> Schema {
+        Schema::new(Kind::Integer)
+    }
+
+    pub fn string() -> Schema {
+        Schema::new(Kind::String)
+    }
+
+    /// A fixed-length array, such as the encoding of a Rust tuple.
+    pub fn tuple(items: Vec<Schema>) -> Schema {
+        Schema::new(Kind::Tuple(items))
+    }
+
+    /// An object with no fields declared yet. Fields which aren't
+    /// declared are ignored, the same way `json::decode()` does.
+    pub fn object() -> Schema {
+        Schema::new(Kind::Object(Vec::new()))
+    }
+
+    pub fn field(mut self, name: &str, schema: Schema) -> Schema {
+        if let Kind::Object(ref mut fields) = self.kind {
+            fields.push((name.to_string(), schema));
+        }
+        self
+    }
+
+    /// Allows `null` or a missing object field, which decode to `None`.
+    pub fn nullable(mut self) -> Schema {
+        self.nullable = true;
+        self
+    }
+
+    pub fn non_empty(mut self) -> Schema {
+        self.checks.push(Check::NonEmpty);
+        self
+    }
+
+    /// An inclusive range of allowed integers.
+    pub fn range(mut self, min: i64, max: i64) -> Schema {
+        self.checks.push(Check::Range(min, max));
+        self
+    }
+
+    pub fn positive(self) -> Schema {
+        self.range(1, i64::max_value())
+    }
+
+    pub fn url(mut self) -> Schema {
+        self.checks.push(Check::Url);
+        self
+    }
+
+    /// Collects every violation in `json`, in document order.
+    pub fn validate(&self, json: &Json) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        self.check(json, "", &mut violations);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Parses `input`, validates it and only then decodes it into `T`.
+    pub fn decode<T: Decodable>(&self, input: &str) -> Result<T, InputError> {
+        let json = Json::from_str(input).map_err(InputError::Syntax)?;
+        self.validate(&json).map_err(InputError::Invalid)?;
+        let mut decoder = json::Decoder::new(json);
+        T::decode(&mut decoder).map_err(InputError::Decode)
+    }
+
+    fn check(&self, json: &Json, path: &str, violations: &mut Vec<Violation>) {
+        let expected = match (&self.kind, json) {
+            (_, &Json::Null) if self.nullable => return,
+            (&Kind::Boolean, &Json::Boolean(_)) => return,
+            (&Kind::Integer, &Json::I64(n)) => return self.check_integer(n, path, violations),
+            (&Kind::Integer, &Json::U64(n)) if n <= i64::max_value() as u64 => {
+                return self.check_integer(n as i64, path, violations)
+            }
+            (&Kind::Integer, &Json::U64(n)) => {
+                return violate(violations, path, format!("{} is out of range", n))
+            }
+            (&Kind::String, &Json::String(ref s)) => return self.check_string(s, path, violations),
+            (&Kind::Tuple(ref items), &Json::Array(ref array)) => {
+                if items.len() != array.len() {
+                    let message = format!("expected {} items, found {}", items.len(), array.len());
+                    return violate(violations, path, message);
+                }
+                for (index, (item, value)) in items.iter().zip(array).enumerate() {
+                    item.check(value, &format!("{}/{}", path, index), violations);
+                }
+                return;
+            }
+            (&Kind::Object(ref fields), &Json::Object(ref object)) => {
+                for &(ref name, ref schema) in fields {
+                    let field_path = format!("{}/{}", path, escape_token(name));
+                    match object.get(name) {
+                        Some(value) => schema.check(value, &field_path, violations),
+                        None if schema.nullable => {}
+                        None => violate(violations, &field_path, "missing required field".to_string()),
+                    }
+                }
+                return;
+            }
+            (&Kind::Boolean, _) => "a boolean",
+            (&Kind::Integer, _) => "an integer",
+            (&Kind::String, _) => "a string",
+            (&Kind::Tuple(_), _) => "an array",
+            (&Kind::Object(_), _) => "an object",
+        };
+        let message = format!("expected {}, found {}", expected, type_name(json));
+        violate(violations, path, message);
+    }
+
+    fn check_integer(&self, n: i64, path: &str, violations: &mut Vec<Violation>) {
+        for check in &self.checks {
+            if let Check::Range(min, max) = *check {
+                if n < min || n > max {
+                    let message = if max == i64::max_value() {
+                        format!("must be at least {}, found {}", min, n)
+                    } else {
+                        format!("must be between {} and {}, found {}", min, max, n)
+                    };
+                    violate(violations, path, message);
+                }
+            }
+        }
+    }
+
+    fn check_string(&self, s: &str, path: &str, violations: &mut Vec<Violation>) {
+        for check in &self.checks {
+            match *check {
+                Check::NonEmpty if s.trim().is_empty() => {
+                    violate(violations, path, "must not be empty".to_string())
+                }
+                Check::Url => if let Err(e) = Url::parse(s) {
+                    violate(violations, path, format!("{:?} is not a valid URL: {}", s, e))
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json::Json;
+
+    use super::*;
+
+    fn violations(schema: &Schema, input: &str) -> Vec<String> {
+        let json = Json::from_str(input).unwrap();
+        match schema.validate(&json) {
+            Ok(()) => Vec::new(),
+            Err(violations) => violations.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn non_empty() {
+        let schema = Schema::string().non_empty();
+        assert!(violations(&schema, r#""Alice""#).is_empty());
+        assert_eq!(
+            violations(&schema, r#""""#),
+            ["document: must not be empty"]
+        );
+        assert_eq!(
+            violations(&schema, r#"" \t""#),
+            ["document: must not be empty"]
+        );
+        assert_eq!(
+            violations(&schema, "1"),
+            ["document: expected a string, found an integer"]
+        );
+    }
+
+    #[test]
+    fn range() {
+        let schema = Schema::integer().range(-5, 5);
+        assert!(violations(&schema, "-5").is_empty());
+        assert!(violations(&schema, "5").is_empty());
+        assert_eq!(
+            violations(&schema, "-6"),
+            ["document: must be between -5 and 5, found -6"]
+        );
+        assert_eq!(
+            violations(&schema, "6"),
+            ["document: must be between -5 and 5, found 6"]
+        );
+        assert_eq!(
+            violations(&schema, "18446744073709551615"),
+            ["document: 18446744073709551615 is out of range"]
+        );
+        assert_eq!(
+            violations(&schema, "1.5"),
+            ["document: expected an integer, found a number"]
+        );
+    }
+
+    #[test]
+    fn positive() {
+        let schema = Schema::integer().positive();
+        assert!(violations(&schema, "1").is_empty());
+        assert!(violations(&schema, "9223372036854775807").is_empty());
+        assert_eq!(
+            violations(&schema, "0"),
+            ["document: must be at least 1, found 0"]
+        );
+        assert_eq!(
+            violations(&schema, "-3"),
+            ["document: must be at least 1, found -3"]
+        );
+    }
+
+    #[test]
+    fn url() {
+        let schema = Schema::string().url();
+        assert!(violations(&schema, r#""https://example.com/a.png""#).is_empty());
+        assert_eq!(
+            violations(&schema, r#""a.png""#),
+            ["document: \"a.png\" is not a valid URL: relative URL without a base"]
+        );
+    }
+
+    #[test]
+    fn tuple() {
+        let schema = Schema::tuple(vec![Schema::integer().positive(), Schema::boolean()]);
+        assert!(violations(&schema, "[1, true]").is_empty());
+        assert_eq!(
+            violations(&schema, "[1]"),
+            ["document: expected 2 items, found 1"]
+        );
+        assert_eq!(
+            violations(&schema, "[0, 1]"),
+            [
+                "/0: must be at least 1, found 0",
+                "/1: expected a boolean, found an integer"
+            ]
+        );
+        assert_eq!(
+            violations(&schema, "{}"),
+            ["document: expected an array, found an object"]
+        );
+    }
+
+    #[test]
+    fn nullable() {
+        let schema = Schema::object()
+            .field("required", Schema::integer())
+            .field("optional", Schema::integer().nullable());
+        assert!(violations(&schema, r#"{"required": 1, "optional": null}"#).is_empty());
+        assert!(violations(&schema, r#"{"required": 1}"#).is_empty());
+        assert_eq!(
+            violations(&schema, r#"{"required": null, "optional": "1"}"#),
+            [
+                "/required: expected an integer, found null",
+                "/optional: expected an integer, found a string"
+            ]
+        );
+        assert_eq!(
+            violations(&schema, "{}"),
+            ["/required: missing required field"]
+        );
+    }
+
+    #[test]
+    fn nested_paths() {
+        let schema = Schema::object()
+            .field("name", Schema::string().non_empty())
+            .field(
+                "avatar",
+                Schema::object()
+                    .field("url", Schema::string().url())
+                    .field(
+                        "dimensions",
+                        Schema::tuple(vec![
+                            Schema::integer().positive(),
+                            Schema::integer().positive(),
+                        ]),
+                    )
+                    .nullable(),
+            )
+            .field("a/b~c", Schema::boolean().nullable());
+        let input = r#"{"name": "", "a/b~c": 1,
+                        "avatar": {"url": "nope", "dimensions": [640, 0], "extra": 1}}"#;
+        assert_eq!(
+            violations(&schema, input),
+            [
+                "/name: must not be empty",
+                "/avatar/url: \"nope\" is not a valid URL: relative URL without a base",
+                "/avatar/dimensions/1: must be at least 1, found 0",
+                "/a~1b~0c: expected a boolean, found an integer"
+            ]
+        );
+        assert!(violations(&schema, r#"{"name": "Alice", "avatar": null}"#).is_empty());
+    }
+
+    #[derive(Debug, PartialEq, RustcDecodable)]
+    struct Counter {
+        name: String,
+        count: u32,
+    }
+
+    #[test]
+    fn decode() {
+        let schema = Schema::object()
+            .field("name", Schema::string().non_empty())
+            .field("count", Schema::integer().range(0, 10));
+        let counter: Counter = schema.decode(r#"{"name": "a", "count": 3}"#).unwrap();
+        assert_eq!(
+            counter,
+            Counter {
+                name: "a".to_string(),
+                count: 3
+            }
+        );
+        match schema.decode::<Counter>(r#"{"name": "a", "count": 30}"#) {
+            Err(InputError::Invalid(ref violations)) => {
+                assert_eq!(
+                    violations,
+                    &[Violation {
+                        path: "/count".to_string(),
+                        message: "must be between 0 and 10, found 30".to_string(),
+                    }]
+                )
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match schema.decode::<Counter>(r#"{"name": "a","#) {
+            Err(InputError::Syntax(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // The schema only checks the declared fields, decoding catches the rest.
+        match Schema::object().decode::<Counter>(r#"{"name": "a"}"#) {
+            Err(InputError::Decode(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use std::cmp::max;
+use std::io::{self, Read};
+use std::mem;
+use std::ops::AddAssign;
+use std::str;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Counts {
+    pub lines: usize,
+    pub words: usize,
+    pub chars: usize,
+    pub bytes: usize,
+    pub max_line_length: usize,
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, other: Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+        self.max_line_length = max(self.max_line_length, other.max_line_length);
+    }
+}
+
+/// Display width of a printable character, roughly what `wcwidth` says:
+/// combining marks, Hangul medial vowels and final consonants and the
+/// invisible formatting characters take no space, East Asian wide
+/// characters take two.
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300...0x036F | 0x1160...0x11FF | 0x200B...0x200F | 0x202A...0x202E |
+        0x2060...0x2064 => 0,
+        0x1100...0x115F | 0x2E80...0x303E | 0x3041...0x33FF | 0x3400...0x4DBF |
+        0x4E00...0x9FFF | 0xA000...0xA4CF | 0xAC00...0xD7A3 | 0xF900...0xFAFF |
+        0xFE30...0xFE4F | 0xFF00...0xFF60 | 0xFFE0...0xFFE6 | 0x1F300...0x1F64F |
+        0x1F900...0x1F9FF | 0x20000...0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// Whitespace that separates words. glibc's `iswspace` leaves out the
+/// non-breaking spaces, but GNU `wc` counts them (and the word joiner) as
+/// separators on top of it.
+fn is_separator(c: char) -> bool {
+    match c {
+        '\u{A0}' | '\u{2007}' | '\u{202F}' | '\u{2060}' => true,
+        // Not spaces for glibc, see `is_printable()`.
+        '\u{85}' | '\u{2028}' | '\u{2029}' => false,
+        c => c.is_whitespace(),
+    }
+}
+
+/// Whether `c` can be part of a word. For glibc, control characters (NEL
+/// included) and the Unicode line and paragraph separators are neither
+/// part of a word nor separate words, they are simply skipped.
+fn is_printable(c: char) -> bool {
+    !c.is_control() && c != '\u{2028}' && c != '\u{2029}'
+}
+
+fn next_tab_stop(column: usize) -> usize {
+    column + 8 - column % 8
+}
+
+/// How far a piece of a line moves the cursor. Where it ends up depends on
+/// the starting column only up to the first tab, so a column `c` maps to
+/// `c + before_tab`, or to `next_tab_stop(c + before_tab) + after_tab`.
+#[derive(Clone, Copy, Debug, Default)]
+struct Span {
+    before_tab: usize,
+    after_tab: Option<usize>,
+}
+
+impl Span {
+    fn advance(&mut self, width: usize) {
+        match self.after_tab {
+            Some(ref mut after_tab) => *after_tab += width,
+            None => self.before_tab += width,
+        }
+    }
+
+    fn tab(&mut self) {
+        self.after_tab = Some(match self.after_tab {
+            Some(after_tab) => next_tab_stop(after_tab),
+            None => 0,
+        });
+    }
+
+    fn end_column(&self, start: usize) -> usize {
+        match self.after_tab {
+            Some(after_tab) => next_tab_stop(start + self.before_tab) + after_tab,
+            None => start + self.before_tab,
+        }
+    }
+
+    fn then(self, other: Span) -> Span {
+        match (self.after_tab, other.after_tab) {
+            (None, _) => Span {
+                before_tab: self.before_tab + other.before_tab,
+                after_tab: other.after_tab,
+            },
+            (Some(after_tab), None) => Span {
+                before_tab: self.before_tab,
+                after_tab: Some(after_tab + other.before_tab),
+            },
+            (Some(after_tab), Some(other_after_tab)) => Span {
+                before_tab: self.before_tab,
+                after_tab: Some(next_tab_stop(after_tab + other.before_tab) + other_after_tab),
+            },
+        }
+    }
+}
+
+/// Counts for a contiguous piece of input that may start or end in the
+/// middle of a line or a word. Pieces counted separately are glued back
+/// together with `then`, in input order.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Partial {
+    counts: Counts,
+    /// The line the piece starts in, if it also ends within the piece.
+    head: Option<Span>,
+    /// Whatever follows the last line break.
+    tail: Span,
+    /// Whether the first and last characters that decide word boundaries
+    /// were part of a word, `None` if there weren't any.
+    starts_in_word: Option<bool>,
+    ends_in_word: Option<bool>,
+}
+
+impl Partial {
+    pub fn then(self, other: Partial) -> Partial {
+        let mut counts = self.counts;
+        counts += other.counts;
+        if self.ends_in_word == Some(true) && other.starts_in_word == Some(true) {
+            counts.words -= 1;
+        }
+        let (head, tail) = match (self.head, other.head) {
+            (head, None) => (head, self.tail.then(other.tail)),
+            (None, Some(other_head)) => (Some(self.tail.then(other_head)), other.tail),
+            (Some(head), Some(other_head)) => {
+                let width = self.tail.then(other_head).end_column(0);
+                counts.max_line_length = max(counts.max_line_length, width);
+                (Some(head), other.tail)
+            }
+        };
+        Partial {
+            counts: counts,
+            head: head,
+            tail: tail,
+            starts_in_word: self.starts_in_word.or(other.starts_in_word),
+            ends_in_word: other.ends_in_word.or(self.ends_in_word),
+        }
+    }
+
+    /// Final counts, treating the piece as a whole input.
+    pub fn counts(&self) -> Counts {
+        let mut counts = self.counts;
+        if let Some(head) = self.head {
+            counts.max_line_length = max(counts.max_line_length, head.end_column(0));
+        }
+        counts.max_line_length = max(counts.max_line_length, self.tail.end_column(0));
+        counts
+    }
+}
+
+/// Incremental counter following the rules of GNU `wc`. Bytes that aren't
+/// valid UTF-8 only add to the byte count.
+pub struct Counter {
+    partial: Partial,
+    pending: Vec<u8>,
+}
+
+impl Counter {
+    pub fn new() -> Counter {
+        Counter {
+            partial: Partial::default(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn set_in_word(&mut self, in_word: bool) {
+        let partial = &mut self.partial;
+        if in_word && partial.ends_in_word != Some(true) {
+            partial.counts.words += 1;
+        }
+        if partial.starts_in_word.is_none() {
+            partial.starts_in_word = Some(in_word);
+        }
+        partial.ends_in_word = Some(in_word);
+    }
+
+    fn end_line(&mut self) {
+        let partial = &mut self.partial;
+        let line = mem::replace(&mut partial.tail, Span::default());
+        if partial.head.is_none() {
+            partial.head = Some(line);
+        } else {
+            partial.counts.max_line_length =
+                max(partial.counts.max_line_length, line.end_column(0));
+        }
+    }
+
+    fn count_str(&mut self, s: &str) {
+        for c in s.chars() {
+            self.partial.counts.chars += 1;
+            match c {
+                '\n' => {
+                    self.partial.counts.lines += 1;
+                    self.end_line();
+                    self.set_in_word(false);
+                }
+                '\r' | '\x0c' => {
+                    self.end_line();
+                    self.set_in_word(false);
+                }
+                '\t' => {
+                    self.partial.tail.tab();
+                    self.set_in_word(false);
+                }
+                c if is_separator(c) => {
+                    if c != '\x0b' {
+                        self.partial.tail.advance(char_width(c));
+                    }
+                    self.set_in_word(false);
+                }
+                c if !is_printable(c) => {}
+                c => {
+                    self.partial.tail.advance(char_width(c));
+                    self.set_in_word(true);
+                }
+            }
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.partial.counts.bytes += data.len();
+        let joined;
+        let mut input = data;
+        if !self.pending.is_empty() {
+            let mut bytes = mem::replace(&mut self.pending, Vec::new());
+            bytes.extend_from_slice(data);
+            joined = bytes;
+            input = &joined;
+        }
+        loop {
+            match str::from_utf8(input) {
+                Ok(s) => {
+                    self.count_str(s);
+                    return;
+                }
+                Err(e) => {
+                    let (valid, rest) = input.split_at(e.valid_up_to());
+                    self.count_str(str::from_utf8(valid).unwrap());
+                    match e.error_len() {
+                        Some(len) => input = &rest[len..],
+                        None => {
+                            self.pending = rest.to_vec();
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn finish(self) -> Partial {
+        self.partial
+    }
+}
+
+pub fn count_partial<R: Read>(mut reader: R) -> io::Result<Partial> {
+    let mut counter = Counter::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        counter.update(&buf[..n]);
+    }
+    Ok(counter.finish())
+}
+
+pub fn count<R: Read>(reader: R) -> io::Result<Counts> {
+    count_partial(reader).map(|partial| partial.counts())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Multi-byte characters of every length, runs of mixed whitespace,
+    // tabs in the middle of wide text and lines of different widths.
+    const SAMPLE: &'static str = "héllo  wörld\t一二三 😀x\n\
+                                  \u{A0}\u{3000} \tnbsp\u{202F}run\u{2028}on \r\n\
+                                  \n  a\u{301}\t\tb\x0bc\x0cd\u{85}e\u{2060}\u{2060}f   \n\
+                                  ends without newline 😀😀";
+
+    fn sequential(input: &[u8]) -> Counts {
+        count(input).unwrap()
+    }
+
+    fn partial(input: &[u8]) -> Partial {
+        let mut counter = Counter::new();
+        counter.update(input);
+        counter.finish()
+    }
+
+    #[test]
+    fn updates_split_inside_characters() {
+        let input = SAMPLE.as_bytes();
+        let expected = sequential(input);
+        for i in 0..input.len() + 1 {
+            let mut counter = Counter::new();
+            counter.update(&input[..i]);
+            counter.update(&input[i..]);
+            assert_eq!(counter.finish().counts(), expected, "split at byte {}", i);
+        }
+    }
+
+    #[test]
+    fn updates_byte_by_byte() {
+        let input = SAMPLE.as_bytes();
+        let mut counter = Counter::new();
+        for byte in input {
+            counter.update(&[*byte]);
+        }
+        assert_eq!(counter.finish().counts(), sequential(input));
+    }
+
+    #[test]
+    fn partials_split_in_two() {
+        let expected = sequential(SAMPLE.as_bytes());
+        for (i, _) in SAMPLE.char_indices() {
+            let (first, second) = SAMPLE.split_at(i);
+            let merged = partial(first.as_bytes()).then(partial(second.as_bytes()));
+            assert_eq!(merged.counts(), expected, "split at byte {}", i);
+        }
+    }
+
+    #[test]
+    fn partials_split_in_three() {
+        let input = SAMPLE.as_bytes();
+        let expected = sequential(input);
+        let boundaries: Vec<usize> = SAMPLE.char_indices().map(|(i, _)| i).collect();
+        for &i in &boundaries {
+            for &j in boundaries.iter().filter(|&&j| j >= i) {
+                let merged = partial(&input[..i])
+                    .then(partial(&input[i..j]))
+                    .then(partial(&input[j..]));
+                assert_eq!(merged.counts(), expected, "split at bytes {} and {}", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn whitespace_runs_across_pieces() {
+        let input = "a \u{A0}\t\u{3000}  b";
+        let expected = sequential(input.as_bytes());
+        assert_eq!(expected.words, 2);
+        let pieces: Vec<Partial> = input
+            .chars()
+            .map(|c| partial(c.to_string().as_bytes()))
+            .collect();
+        let merged = pieces
+            .into_iter()
+            .fold(Partial::default(), |acc, piece| acc.then(piece));
+        assert_eq!(merged.counts(), expected);
+    }
+
+    #[test]
+    fn invalid_bytes_only_count_as_bytes() {
+        let counts = sequential(b"ab\xFFcd \xE4\xB8 e\n");
+        assert_eq!(counts.bytes, 11);
+        assert_eq!(counts.chars, 8);
+        assert_eq!(counts.words, 2);
+        assert_eq!(counts.lines, 1);
+    }
+}